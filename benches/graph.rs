@@ -0,0 +1,119 @@
+// Copyright (C) 2026  Rafael Carvalho <contact@rafaelrc.com>
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Benchmarks of the [graph::PWGraph] walk over synthetic graphs of thousands of objects, built
+//! without a connection to the PipeWire server.
+//!
+//! Each sink is fed by a filter node, that is fed by many client streams. The benchmarks compare
+//! walking every sink again after each change to only walking the sinks affected by it.
+//!
+//! Run with `cargo bench`.
+
+use std::{hint::black_box, time::Instant};
+
+#[allow(dead_code)]
+#[path = "../src/pipewire_connection/graph/mod.rs"]
+mod graph;
+use graph::object::{Direction, Id};
+
+#[allow(dead_code)]
+#[path = "../tests/common/mod.rs"]
+mod common;
+use common::{TestGraph, config};
+
+/// Number of times each benchmark is run
+const ITERATIONS: u32 = 1000;
+
+/// Builds a graph where each sink is fed by a filter node, that is fed by `clients` client
+/// streams. Returns the graph and the ids of the client links.
+fn synthetic_graph(sinks: usize, clients: usize) -> (TestGraph, Vec<Id>) {
+    let mut g = TestGraph::new(config());
+    let mut client_links = Vec::new();
+
+    for sink in 0..sinks {
+        let sink_node = g.add_node(&format!("sink-{sink}"), Some("Audio/Sink"));
+        let sink_input = g.add_port(sink_node, Direction::Input);
+
+        let filter_node = g.add_node(&format!("filter-{sink}"), Some("Stream/Input/Audio"));
+        let filter_input = g.add_port(filter_node, Direction::Input);
+        let filter_output = g.add_port(filter_node, Direction::Output);
+        g.add_link(filter_output, sink_input);
+
+        for client in 0..clients {
+            let client_node = g.add_node(
+                &format!("client-{sink}-{client}"),
+                Some("Stream/Output/Audio"),
+            );
+            let client_output = g.add_port(client_node, Direction::Output);
+            client_links.push(g.add_link(client_output, filter_input));
+        }
+    }
+
+    (g, client_links)
+}
+
+/// Runs a benchmark and prints the average time of each iteration
+fn bench(name: &str, mut f: impl FnMut(u32)) {
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        f(i);
+    }
+    let average = start.elapsed() / ITERATIONS;
+    println!("{name:<48} {:>12.3?}", average);
+}
+
+/// Benchmarks a single link change followed by the evaluation of the graph, as done for every
+/// PipeWire event, when every sink is walked again and when only the affected sink is.
+fn bench_link_change(sinks: usize, clients: usize) {
+    let (mut synthetic_graph, links) = synthetic_graph(sinks, clients);
+    let objects = synthetic_graph.next_id;
+
+    bench(
+        &format!("full walk, {sinks} sinks, {objects} objects"),
+        |i| {
+            // Each link is paused and then resumed, so that every iteration changes the graph
+            let link = links[(i / 2) as usize % links.len()];
+            assert!(synthetic_graph.set_link_active(link, i % 2 == 1));
+            synthetic_graph.graph.clear_sink_activity();
+            black_box(synthetic_graph.graph.get_active_nodes());
+        },
+    );
+
+    bench(
+        &format!("incremental, {sinks} sinks, {objects} objects"),
+        |i| {
+            // Each link is paused and then resumed, so that every iteration changes the graph
+            let link = links[(i / 2) as usize % links.len()];
+            assert!(synthetic_graph.set_link_active(link, i % 2 == 1));
+            black_box(synthetic_graph.graph.get_active_nodes());
+        },
+    );
+
+    bench(
+        &format!("no change, {sinks} sinks, {objects} objects"),
+        |_| {
+            black_box(synthetic_graph.graph.get_active_nodes());
+        },
+    );
+}
+
+fn main() {
+    let start = Instant::now();
+    bench_link_change(1, 1000);
+    bench_link_change(10, 100);
+    bench_link_change(50, 100);
+    println!("Total time: {:.3?}", start.elapsed());
+}
@@ -14,12 +14,16 @@
 
 // SPDX-License-Identifier: GPL-3.0-only
 
-use std::error::Error;
+use std::{collections::HashMap, error::Error};
 
 use log::{debug, error, info};
 use zbus::{blocking::Connection, proxy};
 
 use super::IdleInhibitor;
+use crate::pipewire_connection::graph::ActivePath;
+
+/// Reason used when there are no known active paths
+const DEFAULT_REASON: &str = "Media is being played";
 
 #[proxy(
     interface = "org.freedesktop.ScreenSaver",
@@ -31,21 +35,47 @@ trait ScreenSaver {
     fn UnInhibit(&self, cookie: u32) -> zbus::Result<()>;
 }
 
+/// An inhibition held through the D-Bus service
+struct Inhibition {
+    cookie: u32,
+    reason: String,
+}
+
+/// Idle inhibitor using the `org.freedesktop.ScreenSaver` D-Bus interface.
+///
+/// By default, a single inhibition is held for the whole program. If `per_app` is set, one
+/// inhibition is held for each playing application instead, using its name as the application
+/// name, so that it is shown by desktop environments that list inhibitors.
 pub struct DbusIdleInhibitor<'a> {
     _dbus_connection: Connection,
     dbus_proxy: ScreenSaverProxyBlocking<'a>,
-    cookie: Option<u32>,
+    reason_template: String,
+    per_app: bool,
+    is_idle_inhibited: bool,
+    active_paths: Vec<ActivePath>,
+    /// Inhibitions currently held, by application name
+    inhibitions: HashMap<String, Inhibition>,
 }
 
 impl<'a> DbusIdleInhibitor<'a> {
-    pub fn new() -> Result<DbusIdleInhibitor<'a>, Box<dyn Error>> {
+    /// Creates the inhibitor. The reason sent with inhibit requests is built from the template,
+    /// where `{app_names}`, `{sinks}` and `{paths}` are replaced by the active apps, the sinks they
+    /// play on and the full active paths.
+    pub fn new(
+        reason_template: &str,
+        per_app: bool,
+    ) -> Result<DbusIdleInhibitor<'a>, Box<dyn Error>> {
         let dbus_connection = Connection::session()?;
         let dbus_proxy = ScreenSaverProxyBlocking::new(&dbus_connection)?;
 
         let mut dbus_idle_inhibitor = DbusIdleInhibitor {
             _dbus_connection: dbus_connection,
             dbus_proxy,
-            cookie: None,
+            reason_template: String::from(reason_template),
+            per_app,
+            is_idle_inhibited: false,
+            active_paths: Vec::new(),
+            inhibitions: HashMap::new(),
         };
 
         dbus_idle_inhibitor.inhibit()?;
@@ -54,26 +84,129 @@ impl<'a> DbusIdleInhibitor<'a> {
         debug!(target: "DbusIdleInhibitor::new", "DBus Idle Inhibitor created");
         Ok(dbus_idle_inhibitor)
     }
+
+    /// Fills the reason template with the active paths. If there are none, e.g. when the schedule
+    /// forces idle to be inhibited, a generic reason is used.
+    fn format_reason(&self, active_paths: &[&ActivePath]) -> String {
+        if active_paths.is_empty() {
+            return String::from(DEFAULT_REASON);
+        }
+
+        let app_names = unique(active_paths.iter().filter_map(|path| path.client()));
+        let sinks = unique(active_paths.iter().filter_map(|path| path.sink()));
+        let paths: Vec<String> = active_paths
+            .iter()
+            .map(|path| path.nodes.join(" → "))
+            .collect();
+
+        self.reason_template
+            .replace("{app_names}", &app_names.join(", "))
+            .replace("{sinks}", &sinks.join(", "))
+            .replace("{paths}", &paths.join(", "))
+    }
+
+    /// Gets the inhibitions that should be held, as pairs of application name and reason. In the
+    /// per app mode, the program name is only used if idle is inhibited without any playing app.
+    fn get_wanted_inhibitions(&self) -> HashMap<String, String> {
+        let mut wanted_inhibitions = HashMap::new();
+        if !self.is_idle_inhibited {
+            return wanted_inhibitions;
+        }
+
+        if self.per_app {
+            for app_name in unique(self.active_paths.iter().filter_map(ActivePath::client)) {
+                let app_paths: Vec<&ActivePath> = self
+                    .active_paths
+                    .iter()
+                    .filter(|path| path.client() == Some(app_name))
+                    .collect();
+                wanted_inhibitions.insert(String::from(app_name), self.format_reason(&app_paths));
+            }
+        }
+
+        if wanted_inhibitions.is_empty() {
+            let active_paths: Vec<&ActivePath> = self.active_paths.iter().collect();
+            wanted_inhibitions.insert(
+                String::from(env!("CARGO_PKG_NAME")),
+                self.format_reason(&active_paths),
+            );
+        }
+
+        wanted_inhibitions
+    }
+
+    /// Requests and releases inhibitions until the held ones match the wanted ones. New
+    /// inhibitions are requested before old ones are released, so that idle is never uninhibited
+    /// in between.
+    fn update_inhibitions(&mut self) -> Result<(), Box<dyn Error>> {
+        let wanted_inhibitions = self.get_wanted_inhibitions();
+
+        for (app_name, reason) in &wanted_inhibitions {
+            if self
+                .inhibitions
+                .get(app_name)
+                .is_some_and(|inhibition| inhibition.reason == *reason)
+            {
+                continue;
+            }
+
+            let cookie = self.dbus_proxy.Inhibit(app_name, reason)?;
+            debug!(target: "DbusIdleInhibitor::update_inhibitions", "Inhibited idle for {app_name}: \"{reason}\"");
+            let old_inhibition = self.inhibitions.insert(
+                app_name.clone(),
+                Inhibition {
+                    cookie,
+                    reason: reason.clone(),
+                },
+            );
+            if let Some(old_inhibition) = old_inhibition {
+                self.dbus_proxy.UnInhibit(old_inhibition.cookie)?;
+            }
+        }
+
+        let old_app_names: Vec<String> = self
+            .inhibitions
+            .keys()
+            .filter(|app_name| !wanted_inhibitions.contains_key(*app_name))
+            .cloned()
+            .collect();
+        for app_name in old_app_names {
+            if let Some(inhibition) = self.inhibitions.remove(&app_name) {
+                self.dbus_proxy.UnInhibit(inhibition.cookie)?;
+                debug!(target: "DbusIdleInhibitor::update_inhibitions", "Uninhibited idle for {app_name}");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects names, removing duplicates while keeping their order
+fn unique<'a>(names: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut unique_names = Vec::new();
+    for name in names {
+        if !unique_names.contains(&name) {
+            unique_names.push(name);
+        }
+    }
+    unique_names
 }
 
 impl Drop for DbusIdleInhibitor<'_> {
     fn drop(&mut self) {
-        if let Some(cookie) = self.cookie {
-            if let Err(error) = self.dbus_proxy.UnInhibit(cookie) {
+        for (_, inhibition) in self.inhibitions.drain() {
+            if let Err(error) = self.dbus_proxy.UnInhibit(inhibition.cookie) {
                 error!(target: "DbusIdleInhibitor::drop", "{error}");
             }
-            self.cookie = None;
         }
     }
 }
 
 impl IdleInhibitor for DbusIdleInhibitor<'_> {
     fn inhibit(&mut self) -> Result<(), Box<dyn Error>> {
-        if self.cookie.is_none() {
-            self.cookie = Some(
-                self.dbus_proxy
-                    .Inhibit(env!("CARGO_PKG_NAME"), "Media is being played")?,
-            );
+        if !self.is_idle_inhibited {
+            self.is_idle_inhibited = true;
+            self.update_inhibitions()?;
             info!(target: "DbusIdleInhibitor::inhibit", "Idle Inhibitor was ENABLED");
         }
 
@@ -81,12 +214,19 @@ impl IdleInhibitor for DbusIdleInhibitor<'_> {
     }
 
     fn uninhibit(&mut self) -> Result<(), Box<dyn Error>> {
-        if let Some(cookie) = self.cookie {
-            self.dbus_proxy.UnInhibit(cookie)?;
-            self.cookie = None;
+        if self.is_idle_inhibited {
+            self.is_idle_inhibited = false;
+            self.update_inhibitions()?;
             info!(target: "DbusIdleInhibitor::uninhibit", "Idle Inhibitor was DISABLED");
         }
 
         Ok(())
     }
+
+    /// Updates the reasons and, in the per app mode, the playing apps. If idle is inhibited, the
+    /// inhibitions are updated to match them.
+    fn set_active_paths(&mut self, active_paths: &[ActivePath]) -> Result<(), Box<dyn Error>> {
+        self.active_paths = active_paths.to_vec();
+        self.update_inhibitions()
+    }
 }
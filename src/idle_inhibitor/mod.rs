@@ -1,4 +1,4 @@
-// Copyright (C) 2024-2025  Rafael Carvalho <contact@rafaelrc.com>
+// Copyright (C) 2024-2026  Rafael Carvalho <contact@rafaelrc.com>
 
 // This program is free software: you can redistribute it and/or modify
 // it under the terms of the GNU General Public License version 3 as published by
@@ -16,6 +16,8 @@
 
 use std::error::Error;
 
+use crate::pipewire_connection::graph::ActivePath;
+
 pub mod dbus;
 pub mod dry;
 pub mod wayland;
@@ -34,4 +36,10 @@ pub trait IdleInhibitor {
             self.uninhibit()
         }
     }
+
+    /// Receives the paths of the nodes that currently cause idle to be inhibited. Backends that
+    /// can report why idle is inhibited may store them, by default they are ignored.
+    fn set_active_paths(&mut self, _active_paths: &[ActivePath]) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
 }
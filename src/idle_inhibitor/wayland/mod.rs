@@ -1,4 +1,4 @@
-// Copyright (C) 2023-2025  Rafael Carvalho <contact@rafaelrc.com>
+// Copyright (C) 2023-2026  Rafael Carvalho <contact@rafaelrc.com>
 
 // This program is free software: you can redistribute it and/or modify
 // it under the terms of the GNU General Public License version 3 as published by
@@ -15,6 +15,9 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 //! Connection to the Wayland compositor and manages the Wayland Idle Inhibitor.
+//!
+//! Optionally, user input is also watched through the ext-idle-notify protocol, so that the idle
+//! inhibitor may be re-armed when the user comes back.
 
 use std::collections::HashMap;
 use std::error::Error;
@@ -30,9 +33,10 @@ use nix::unistd::ftruncate;
 use wayland_client::backend::ObjectId;
 use wayland_client::protocol::wl_buffer;
 use wayland_client::protocol::wl_output::WlOutput;
+use wayland_client::protocol::wl_seat::WlSeat;
 use wayland_client::{
     Connection, Dispatch, EventQueue, Proxy, QueueHandle, delegate_noop,
-    globals::{GlobalListContents, registry_queue_init},
+    globals::{BindError, GlobalList, GlobalListContents, registry_queue_init},
     protocol::{
         wl_buffer::WlBuffer,
         wl_compositor::WlCompositor,
@@ -43,6 +47,10 @@ use wayland_client::{
     },
 };
 
+use wayland_protocols::ext::idle_notify::v1::client::{
+    ext_idle_notification_v1::{self, ExtIdleNotificationV1},
+    ext_idle_notifier_v1::ExtIdleNotifierV1,
+};
 use wayland_protocols::wp::idle_inhibit::zv1::client::{
     zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1, zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1,
 };
@@ -58,6 +66,9 @@ use super::IdleInhibitor;
 
 pub type WaylandEventQueue = EventQueue<WaylandIdleInhibitor>;
 
+/// Minimum time without input, in milliseconds, after which input is considered user activity
+const USER_ACTIVITY_IDLE_TIMEOUT: u32 = 1000;
+
 /// Wayland Idle Inhibitor
 #[derive(Debug)]
 pub struct WaylandIdleInhibitor {
@@ -67,8 +78,18 @@ pub struct WaylandIdleInhibitor {
     wlr_layer_shell: ZwlrLayerShellV1,
     idle_inhibit_manager: ZwpIdleInhibitManagerV1,
     outputs: HashMap<u32, Output>, // The u32 key represents a proxy name, the ID used by Wayland
+    _idle_notification: Option<IdleNotification>,
 
     is_idle_inhibited: bool,
+    has_user_activity: bool,
+}
+
+/// Objects used to watch for user activity through the ext-idle-notify protocol
+#[derive(Debug)]
+struct IdleNotification {
+    idle_notifier: ExtIdleNotifierV1,
+    idle_notification: ExtIdleNotificationV1,
+    seat: WlSeat,
 }
 
 /// Wayland [WlOutput] and it's [Surface]
@@ -95,7 +116,10 @@ struct SurfaceIdleInhibitor(ZwpIdleInhibitorV1);
 impl WaylandIdleInhibitor {
     /// Creates an instance by going through the globals list and binding the relevant ones. Does
     /// not create a surface.
-    pub fn new() -> Result<(Self, WaylandEventQueue), Box<dyn Error>> {
+    ///
+    /// If `watch_user_activity` is set, the ext-idle-notify protocol is used to detect user input,
+    /// that may be checked with [Self::take_user_activity].
+    pub fn new(watch_user_activity: bool) -> Result<(Self, WaylandEventQueue), Box<dyn Error>> {
         let connection = Connection::connect_to_env()?;
         let (global_list, mut event_queue) = registry_queue_init::<Self>(&connection)?;
         let qhandle = event_queue.handle();
@@ -124,6 +148,20 @@ impl WaylandIdleInhibitor {
             })
             .collect();
 
+        // Input based re-arm is optional, so compositors without ext-idle-notify are still
+        // supported
+        let idle_notification = if watch_user_activity {
+            match IdleNotification::new(&global_list, &qhandle) {
+                Ok(idle_notification) => Some(idle_notification),
+                Err(err) => {
+                    log::warn!(target: "WaylandIdleInhibitor::new", "Unable to watch user activity, re-arming on user input is disabled: {err}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         let mut obj = Self {
             compositor,
             qhandle,
@@ -131,7 +169,9 @@ impl WaylandIdleInhibitor {
             wlr_layer_shell,
             idle_inhibit_manager,
             outputs,
+            _idle_notification: idle_notification,
             is_idle_inhibited: false,
+            has_user_activity: false,
         };
         obj.init_missing_surfaces();
 
@@ -220,6 +260,13 @@ impl WaylandIdleInhibitor {
     }
 }
 
+impl WaylandIdleInhibitor {
+    /// Returns true if there was user activity since the last call
+    pub fn take_user_activity(&mut self) -> bool {
+        std::mem::take(&mut self.has_user_activity)
+    }
+}
+
 impl IdleInhibitor for WaylandIdleInhibitor {
     fn inhibit(&mut self) -> Result<(), Box<dyn Error>> {
         self.set_inhibit_idle(true)
@@ -230,6 +277,41 @@ impl IdleInhibitor for WaylandIdleInhibitor {
     }
 }
 
+impl IdleNotification {
+    /// Binds the ext-idle-notify and seat globals and starts watching user activity. Fails if the
+    /// compositor does not support the protocol.
+    ///
+    /// Version 1 notifications respect idle inhibitors, including ours, so the user is only seen
+    /// going idle, and then coming back, once idle is no longer inhibited. That is enough to re-arm
+    /// after the maximum duration released idle, but input while idle is inhibited is not reported.
+    fn new(
+        global_list: &GlobalList,
+        qhandle: &QueueHandle<WaylandIdleInhibitor>,
+    ) -> Result<Self, BindError> {
+        let idle_notifier: ExtIdleNotifierV1 = global_list.bind(qhandle, 1..=2, ())?;
+        // Version 5 allows releasing the seat
+        let seat: WlSeat = global_list.bind(qhandle, 1..=5, ())?;
+        // Version 2 allows watching only for input, ignoring idle inhibitors
+        let idle_notification = if idle_notifier.version() >= 2 {
+            idle_notifier.get_input_idle_notification(
+                USER_ACTIVITY_IDLE_TIMEOUT,
+                &seat,
+                qhandle,
+                (),
+            )
+        } else {
+            idle_notifier.get_idle_notification(USER_ACTIVITY_IDLE_TIMEOUT, &seat, qhandle, ())
+        };
+        log::debug!(target: "IdleNotification::new", "Watching user activity with ext-idle-notify v{}", idle_notifier.version());
+
+        Ok(Self {
+            idle_notifier,
+            idle_notification,
+            seat,
+        })
+    }
+}
+
 impl Output {
     fn new(wl_output: WlOutput) -> Self {
         Self {
@@ -367,6 +449,17 @@ impl Drop for WaylandIdleInhibitor {
     }
 }
 
+impl Drop for IdleNotification {
+    fn drop(&mut self) {
+        self.idle_notification.destroy();
+        self.idle_notifier.destroy();
+        // Older seats can not be released, and are only freed with the connection
+        if self.seat.version() >= 5 {
+            self.seat.release();
+        }
+    }
+}
+
 impl Drop for Output {
     fn drop(&mut self) {
         self.wl_output.release();
@@ -487,8 +580,26 @@ impl Dispatch<WlBuffer, ()> for WaylandIdleInhibitor {
     }
 }
 
+/// Subscribes to the [ExtIdleNotificationV1] events, to detect user activity after being idle.
+impl Dispatch<ExtIdleNotificationV1, ()> for WaylandIdleInhibitor {
+    fn event(
+        state: &mut Self,
+        _proxy: &ExtIdleNotificationV1,
+        event: <ExtIdleNotificationV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let ext_idle_notification_v1::Event::Resumed = event {
+            log::debug!(target: "WaylandIdleInhibitor::ExtIdleNotificationV1::Event::Resumed", "User activity detected");
+            state.has_user_activity = true;
+        }
+    }
+}
+
 // Ignore events from these object types.
 delegate_noop!(WaylandIdleInhibitor: ignore WlOutput);
+delegate_noop!(WaylandIdleInhibitor: ignore WlSeat);
 delegate_noop!(WaylandIdleInhibitor: ignore WlShm);
 delegate_noop!(WaylandIdleInhibitor: ignore WlSurface);
 
@@ -497,3 +608,4 @@ delegate_noop!(WaylandIdleInhibitor: ignore WlShmPool);
 delegate_noop!(WaylandIdleInhibitor: ignore ZwlrLayerShellV1);
 delegate_noop!(WaylandIdleInhibitor: ignore ZwpIdleInhibitManagerV1);
 delegate_noop!(WaylandIdleInhibitor: ignore ZwpIdleInhibitorV1);
+delegate_noop!(WaylandIdleInhibitor: ignore ExtIdleNotifierV1);
@@ -1,4 +1,4 @@
-// Copyright (C) 2023-2025  Rafael Carvalho <contact@rafaelrc.com>
+// Copyright (C) 2023-2026  Rafael Carvalho <contact@rafaelrc.com>
 
 // This program is free software: you can redistribute it and/or modify
 // it under the terms of the GNU General Public License version 3 as published by
@@ -16,99 +16,448 @@
 
 //! Helper to manage the idle inhibiting state. This module is used to treat PipeWire events and
 //! send messages if and when idle should be inhibited, treating the minimum sound duration.
+//!
+//! The minimum duration is tracked for each client node individually: idle is only inhibited once
+//! any single stream has been continuously active for longer than the minimum duration.
+//!
+//! To avoid flapping, idle is only uninhibited after media has been silent for the release delay,
+//! and changes to the state, or to the apps that keep it inhibited, are never notified more often
+//! than the minimum toggle interval.
+//!
+//! Continuous inhibition may be limited by a maximum duration, after which idle is released even if
+//! media is still playing. It is only re-armed after media is paused or on user activity.
+//!
+//! Finally, [ScheduleRule]s may force idle to be always or never inhibited, or ignore the node
+//! blacklist, at certain times of the day or days of the week.
+//!
+//! The [ActivePath]s of the nodes that keep idle inhibited are also tracked, logged when the state
+//! changes, and notified to the caller, so that it is possible to tell why idle is inhibited.
+//!
+//! The active nodes of every watched PipeWire remote are tracked together, so that idle is
+//! inhibited if media plays on any of them.
+//!
+//! No decision is made before the first set of active nodes is received, so that the state is not
+//! changed before any PipeWire graph is known. When a remote is disconnected, its nodes are
+//! forgotten. While disconnected from every remote, idle is uninhibited, and decisions are only
+//! made again once a graph is known once more.
+//!
+//! Time is read from a [Clock], that is either the system clock or a virtual one, moved by the
+//! caller, used to replay recorded traces.
 
-use std::sync::{Arc, RwLock};
+use std::collections::{HashMap, HashSet};
 
-use chrono::Duration;
-use log::{debug, trace};
+use chrono::{DateTime, Duration, FixedOffset, Local};
+use log::{debug, info, trace};
 use timer::{Guard, Timer};
 
 use crate::message_queue::MessageQueueSender;
+use crate::pipewire_connection::{
+    RemoteId,
+    graph::{ActiveNode, ActivePath, object::Id},
+};
+use crate::schedule::{ScheduleRule, schedule_state_at};
 
 /// Module Event message type
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum InhibitIdleStateEvent {
     InhibitIdle(bool),
+    /// The paths of the nodes that currently cause idle to be inhibited
+    ActivePaths(Vec<ActivePath>),
+    TimerExpired,
+}
+
+/// Source of the current time of an [InhibitIdleState]
+pub enum Clock {
+    /// The system clock. Future updates of the state are scheduled on a [Timer], that sends
+    /// [InhibitIdleStateEvent::TimerExpired] once they are due.
+    System(Timer),
+    /// A clock that only moves when [InhibitIdleState::set_time] is called. Future updates are only
+    /// stored, and must be triggered by the caller, with [InhibitIdleState::timer_expired], once
+    /// the clock reaches [InhibitIdleState::get_next_update].
+    ///
+    /// The clock keeps the UTC offset of the time it was set to, so that the schedule is evaluated
+    /// in the time zone of the machine a trace was recorded on.
+    Virtual(DateTime<FixedOffset>),
+}
+
+impl Clock {
+    /// Builds the system clock, starting the thread of its [Timer]
+    pub fn system() -> Self {
+        Self::System(Timer::new())
+    }
+}
+
+/// A client node that is playing media
+struct PlayingNode {
+    playing_since: DateTime<Local>,
+    is_blacklisted: bool,
+    paths: Vec<ActivePath>,
 }
 
 /// Manager of the idle inhibit state
 pub struct InhibitIdleState<Msg: From<InhibitIdleStateEvent> + Clone> {
-    inhibit_idle_timout_callback: Timer,
+    clock: Clock,
+    next_update: Option<DateTime<Local>>,
     inhibit_idle_timout_callback_guard: Option<Guard>,
     inhibit_idle_timout: Option<Duration>,
-    is_idle_inhibited: Arc<RwLock<bool>>,
+    release_delay: Option<Duration>,
+    minimum_toggle_interval: Option<Duration>,
+    maximum_duration: Option<Duration>,
+    schedule: Vec<ScheduleRule>,
+    /// Playing nodes, by remote and node id
+    active_nodes: HashMap<(RemoteId, Id), PlayingNode>,
+    active_paths: Vec<ActivePath>,
+    /// Remotes whose graph is known
+    ready_remotes: HashSet<RemoteId>,
+    silent_since: Option<DateTime<Local>>,
+    inhibiting_since: Option<DateTime<Local>>,
+    is_maximum_duration_reached: bool,
+    last_toggle: Option<DateTime<Local>>,
+    last_active_paths_update: Option<DateTime<Local>>,
+    should_inhibit_idle: bool,
+    is_idle_inhibited: bool,
     inhibit_idle_callback: MessageQueueSender<Msg>,
 }
 
 impl<Msg: From<InhibitIdleStateEvent> + Clone + Send + 'static> InhibitIdleState<Msg> {
     pub fn new(
         inhibit_idle_timout: Option<Duration>,
+        release_delay: Option<Duration>,
+        minimum_toggle_interval: Option<Duration>,
+        maximum_duration: Option<Duration>,
+        schedule: Vec<ScheduleRule>,
+        clock: Clock,
         inhibit_idle_callback: MessageQueueSender<Msg>,
     ) -> Self {
         Self {
-            inhibit_idle_timout_callback: Timer::new(),
+            clock,
+            next_update: None,
             inhibit_idle_timout_callback_guard: None,
             inhibit_idle_timout,
-            is_idle_inhibited: Arc::new(RwLock::new(false)),
+            release_delay,
+            minimum_toggle_interval,
+            maximum_duration,
+            schedule,
+            active_nodes: HashMap::new(),
+            active_paths: Vec::new(),
+            ready_remotes: HashSet::new(),
+            silent_since: None,
+            inhibiting_since: None,
+            is_maximum_duration_reached: false,
+            last_toggle: None,
+            last_active_paths_update: None,
+            should_inhibit_idle: false,
+            is_idle_inhibited: false,
             inhibit_idle_callback,
         }
     }
 
-    /// Wrapper function to update the inhibit idle state. It only updates the value if necessary,
-    /// and manages the timer. When a call is made to change the state, it starts a timer with the
-    /// set minimum duration that actually executes the update of the is_idle_inhibited field.
-    pub fn set_is_idle_inhibited(&mut self, is_idle_inhibited: bool) {
-        if let (Some(inhibit_idle_timout), true) = (self.inhibit_idle_timout, is_idle_inhibited) {
-            if self.inhibit_idle_timout_callback_guard.is_some() {
-                trace!(target: "InhibitIdleState::set_is_idle_inhibited", "Update Timer is already running");
-                return;
+    /// Updates the set of client nodes of a remote that are currently playing media.
+    ///
+    /// Nodes that were not active before start counting their playing time from now, while nodes
+    /// that are no longer active are forgotten, so that a stream that stops and starts again has
+    /// to reach the minimum duration once more.
+    pub fn set_active_nodes(&mut self, remote: RemoteId, active_nodes: HashMap<Id, ActiveNode>) {
+        let now = self.now();
+        self.ready_remotes.insert(remote);
+
+        self.active_nodes
+            .retain(|(node_remote, id), _| *node_remote != remote || active_nodes.contains_key(id));
+        for (id, active_node) in active_nodes {
+            let playing_node = self.active_nodes.entry((remote, id)).or_insert_with(|| {
+                trace!(target: "InhibitIdleState::set_active_nodes", "Node {id} of remote {remote} started playing");
+                PlayingNode {
+                    playing_since: now,
+                    is_blacklisted: active_node.is_blacklisted,
+                    paths: Vec::new(),
+                }
+            });
+            playing_node.is_blacklisted = active_node.is_blacklisted;
+            playing_node.paths = active_node.paths;
+        }
+
+        self.update(now);
+    }
+
+    /// Treats the loss of the connection to a PipeWire remote. As it is not known if media is still
+    /// playing on it, its active nodes are forgotten.
+    ///
+    /// If other remotes are still connected, the state is evaluated as usual. Otherwise, idle is
+    /// immediately uninhibited, ignoring the release delay, minimum toggle interval and schedule,
+    /// until a graph is known again.
+    pub fn disconnected(&mut self, remote: RemoteId) {
+        let now = self.now();
+        self.ready_remotes.remove(&remote);
+        self.active_nodes
+            .retain(|(node_remote, _), _| *node_remote != remote);
+
+        if !self.ready_remotes.is_empty() {
+            debug!(target: "InhibitIdleState::disconnected", "Disconnected from PipeWire remote {remote}");
+            self.update(now);
+            return;
+        }
+
+        info!(target: "InhibitIdleState::disconnected", "Disconnected from PipeWire, releasing idle");
+
+        self.next_update = None;
+        self.inhibit_idle_timout_callback_guard = None;
+        self.silent_since = None;
+        self.inhibiting_since = None;
+        self.is_maximum_duration_reached = false;
+        self.should_inhibit_idle = false;
+        self.update_active_paths(Vec::new());
+
+        if self.is_idle_inhibited {
+            self.last_toggle = Some(now);
+            self.update_is_idle_inhibited(false);
+        }
+    }
+
+    /// Re-arms the idle inhibitor after user activity, restarting the count of the maximum
+    /// continuous inhibition time.
+    pub fn rearm(&mut self) {
+        debug!(target: "InhibitIdleState::rearm", "Re-arming idle inhibitor");
+        self.inhibiting_since = None;
+        self.is_maximum_duration_reached = false;
+        self.update(self.now());
+    }
+
+    /// Treats the expiration of the timer started by [Self::update], re-evaluating the state.
+    pub fn timer_expired(&mut self) {
+        trace!(target: "InhibitIdleState::timer_expired", "Timer expired");
+        self.update(self.now());
+    }
+
+    /// Gets the current time of the [Clock]
+    fn now(&self) -> DateTime<Local> {
+        match self.clock {
+            Clock::System(_) => Local::now(),
+            Clock::Virtual(now) => now.with_timezone(&Local),
+        }
+    }
+
+    /// Moves a [Clock::Virtual] to the given instant. The system clock can not be moved, and is not
+    /// changed.
+    pub fn set_time(&mut self, now: DateTime<FixedOffset>) {
+        if let Clock::Virtual(ref mut time) = self.clock {
+            *time = now;
+        }
+    }
+
+    /// Gets the instant at which the state must be evaluated again, if any
+    pub fn get_next_update(&self) -> Option<DateTime<Local>> {
+        self.next_update
+    }
+
+    /// Evaluates if idle should be inhibited at the given instant. Media is considered to be
+    /// playing if any active node has been playing for at least the minimum duration. Once
+    /// inhibited, idle stays inhibited while any node is playing, and is only uninhibited after no
+    /// node has been playing for the release delay. Idle is also uninhibited if it has been
+    /// inhibited for longer than the maximum duration. Lastly, the schedule rules active at the
+    /// instant are applied.
+    ///
+    /// Changes to the state, and to the active paths while idle stays inhibited, are delayed until
+    /// the minimum toggle interval has passed since the last one.
+    ///
+    /// If any of these conditions may change in the future, a timer is started to re-evaluate the
+    /// state at that moment.
+    fn update(&mut self, now: DateTime<Local>) {
+        self.next_update = None;
+        self.inhibit_idle_timout_callback_guard = None;
+        if self.ready_remotes.is_empty() {
+            trace!(target: "InhibitIdleState::update", "Waiting for the PipeWire graph to be known");
+            return;
+        }
+
+        let schedule_state = match self.clock {
+            Clock::System(_) => schedule_state_at(&self.schedule, now),
+            Clock::Virtual(time) => {
+                schedule_state_at(&self.schedule, now.with_timezone(time.offset()))
             }
+        };
+        let mut next_update: Option<DateTime<Local>> = schedule_state.next_change;
 
-            debug!(target: "InhibitIdleState::set_is_idle_inhibited", "Started Timer to inhibit idling");
-            self.inhibit_idle_timout_callback_guard = Some(
-                self.inhibit_idle_timout_callback
-                    .schedule_with_delay(inhibit_idle_timout, {
-                        let is_idle_inhibited_ref = Arc::clone(&self.is_idle_inhibited);
-                        let inhibit_idle_callback = self.inhibit_idle_callback.clone();
-                        move || {
-                            let is_idle_inhibited_ref = &is_idle_inhibited_ref;
-                            Self::update_is_idle_inhibited(
-                                Arc::clone(is_idle_inhibited_ref),
-                                inhibit_idle_callback.clone(),
-                                is_idle_inhibited,
-                            );
-                        }
-                    }),
-            );
+        let playing_since = self
+            .active_nodes
+            .values()
+            .filter(|node| !(schedule_state.node_blacklist && node.is_blacklisted))
+            .map(|node| node.playing_since)
+            .min();
+
+        let is_media_playing = match playing_since {
+            Some(playing_since) => {
+                let inhibit_idle_at =
+                    playing_since + self.inhibit_idle_timout.unwrap_or_else(Duration::zero);
+                if inhibit_idle_at > now {
+                    next_update =
+                        Some(next_update.map_or(inhibit_idle_at, |next| next.min(inhibit_idle_at)));
+                }
+                inhibit_idle_at <= now
+            }
+            None => false,
+        };
+
+        if is_media_playing {
+            self.silent_since = None;
+            self.should_inhibit_idle = true;
+        } else if self.should_inhibit_idle && playing_since.is_some() {
+            // Media that is still playing keeps idle inhibited, even if it has not reached the
+            // minimum duration yet, e.g. when a player opens a new stream for the next track
+            trace!(target: "InhibitIdleState::update", "Media is playing, keeping idle inhibited");
+            self.silent_since = None;
+        } else if self.should_inhibit_idle {
+            let silent_since = *self.silent_since.get_or_insert(now);
+            let release_at = silent_since + self.release_delay.unwrap_or_else(Duration::zero);
+            if release_at <= now {
+                self.silent_since = None;
+                self.should_inhibit_idle = false;
+            } else {
+                trace!(target: "InhibitIdleState::update", "Media is silent, releasing at {release_at}");
+                next_update = Some(next_update.map_or(release_at, |next| next.min(release_at)));
+            }
+        }
+
+        let mut inhibit_idle = self.should_inhibit_idle;
+        if self.should_inhibit_idle {
+            let inhibiting_since = *self.inhibiting_since.get_or_insert(now);
+            if let Some(maximum_duration) = self.maximum_duration {
+                let release_at = inhibiting_since + maximum_duration;
+                if release_at <= now {
+                    if !self.is_maximum_duration_reached {
+                        info!(target: "InhibitIdleState::update", "Maximum inhibition duration reached, releasing idle");
+                        self.is_maximum_duration_reached = true;
+                    }
+                    inhibit_idle = false;
+                } else {
+                    next_update = Some(next_update.map_or(release_at, |next| next.min(release_at)));
+                }
+            }
         } else {
-            if self.inhibit_idle_timout_callback_guard.is_some() {
-                self.inhibit_idle_timout_callback_guard = None
+            self.inhibiting_since = None;
+            self.is_maximum_duration_reached = false;
+        }
+
+        if let Some(inhibit) = schedule_state.inhibit {
+            trace!(target: "InhibitIdleState::update", "Schedule forces idle inhibiting to {inhibit}");
+            inhibit_idle = inhibit;
+        }
+
+        let minimum_toggle_interval = self.minimum_toggle_interval.unwrap_or_else(Duration::zero);
+        let mut should_toggle = false;
+        if inhibit_idle != self.is_idle_inhibited {
+            let toggle_at = self
+                .last_toggle
+                .map(|last_toggle| last_toggle + minimum_toggle_interval)
+                .filter(|toggle_at| *toggle_at > now);
+
+            if let Some(toggle_at) = toggle_at {
+                debug!(target: "InhibitIdleState::update", "Delaying state change until {toggle_at}");
+                next_update = Some(next_update.map_or(toggle_at, |next| next.min(toggle_at)));
+            } else {
+                should_toggle = true;
             }
-            Self::update_is_idle_inhibited(
-                self.is_idle_inhibited.clone(),
-                self.inhibit_idle_callback.clone(),
-                is_idle_inhibited,
-            );
         }
+
+        // While idle is inhibited, nodes that keep it inhibited are reported even before reaching
+        // the minimum duration
+        let minimum_duration = self.inhibit_idle_timout.unwrap_or_else(Duration::zero);
+        let mut active_paths: Vec<ActivePath> = self
+            .active_nodes
+            .values()
+            .filter(|node| !(schedule_state.node_blacklist && node.is_blacklisted))
+            .filter(|node| self.should_inhibit_idle || node.playing_since + minimum_duration <= now)
+            .flat_map(|node| &node.paths)
+            .filter(|path| !(schedule_state.node_blacklist && path.is_blacklisted))
+            .cloned()
+            .collect();
+        active_paths.sort();
+        active_paths.dedup();
+
+        // Changes to the active paths also change the requests made by some idle inhibitors, e.g.
+        // the one D-Bus inhibition held per app, so, while idle stays inhibited, they are limited by
+        // the minimum toggle interval as well
+        if active_paths != self.active_paths {
+            let update_at = self
+                .last_active_paths_update
+                .map(|last_update| last_update + minimum_toggle_interval)
+                .filter(|update_at| *update_at > now && self.is_idle_inhibited && !should_toggle);
+
+            if let Some(update_at) = update_at {
+                debug!(target: "InhibitIdleState::update", "Delaying active paths change until {update_at}");
+                next_update = Some(next_update.map_or(update_at, |next| next.min(update_at)));
+            } else {
+                self.last_active_paths_update = Some(now);
+                self.update_active_paths(active_paths);
+            }
+        }
+
+        if should_toggle {
+            self.last_toggle = Some(now);
+            self.update_is_idle_inhibited(inhibit_idle);
+        }
+
+        let Some(next_update) = next_update else {
+            return;
+        };
+
+        self.next_update = Some(next_update);
+        let Clock::System(ref timer) = self.clock else {
+            return;
+        };
+
+        debug!(target: "InhibitIdleState::update", "Started Timer to update state at {next_update}");
+        self.inhibit_idle_timout_callback_guard = Some(timer.schedule_with_date(next_update, {
+            let inhibit_idle_callback = self.inhibit_idle_callback.clone();
+            move || {
+                inhibit_idle_callback
+                    .send(Msg::from(InhibitIdleStateEvent::TimerExpired))
+                    .unwrap();
+            }
+        }));
     }
 
-    /// Private function that accesses the reference of the state and updates its value
-    fn update_is_idle_inhibited(
-        is_idle_inhibited_ref: Arc<RwLock<bool>>,
-        inhibit_idle_callback: MessageQueueSender<Msg>,
-        is_idle_inhibited: bool,
-    ) {
-        if *is_idle_inhibited_ref.read().unwrap() == is_idle_inhibited {
+    /// Private function that updates the paths of the nodes that cause idle to be inhibited and
+    /// notifies the caller if they were changed
+    fn update_active_paths(&mut self, active_paths: Vec<ActivePath>) {
+        if self.active_paths == active_paths {
+            return;
+        }
+
+        for path in &active_paths {
+            debug!(target: "InhibitIdleState::update_active_paths", "Active path: {path}");
+        }
+        self.active_paths = active_paths;
+        self.inhibit_idle_callback
+            .send(Msg::from(InhibitIdleStateEvent::ActivePaths(
+                self.active_paths.clone(),
+            )))
+            .unwrap();
+    }
+
+    /// Private function that updates the state and notifies the caller if it was changed
+    fn update_is_idle_inhibited(&mut self, is_idle_inhibited: bool) {
+        if self.is_idle_inhibited == is_idle_inhibited {
             trace!(target: "InhibitIdleState", "Tried to update 'is_idle_inhibited', but value is the same");
             return;
         }
 
-        *is_idle_inhibited_ref.write().unwrap() = is_idle_inhibited;
-        inhibit_idle_callback
+        self.is_idle_inhibited = is_idle_inhibited;
+        self.inhibit_idle_callback
             .send(Msg::from(InhibitIdleStateEvent::InhibitIdle(
                 is_idle_inhibited,
             )))
             .unwrap();
         debug!(target: "InhibitIdleState", "Idle inhibting was {}", if is_idle_inhibited { "ENABLED" } else { "DISABLED" });
+
+        if !is_idle_inhibited {
+            info!(target: "InhibitIdleState", "Idle is no longer inhibited");
+        } else if self.active_paths.is_empty() {
+            info!(target: "InhibitIdleState", "Idle is inhibited without active media");
+        } else {
+            info!(target: "InhibitIdleState", "Idle is inhibited by media playing through:");
+            for path in &self.active_paths {
+                info!(target: "InhibitIdleState", "    {path}");
+            }
+        }
     }
 }
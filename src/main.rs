@@ -1,4 +1,4 @@
-// Copyright (C) 2023-2025  Rafael Carvalho <contact@rafaelrc.com>
+// Copyright (C) 2023-2026  Rafael Carvalho <contact@rafaelrc.com>
 
 // This program is free software: you can redistribute it and/or modify
 // it under the terms of the GNU General Public License version 3 as published by
@@ -25,14 +25,17 @@ use std::{
         Arc,
         atomic::{self, AtomicBool},
     },
+    thread,
 };
 
 mod inhibit_idle_state;
-use inhibit_idle_state::{InhibitIdleState, InhibitIdleStateEvent};
+use inhibit_idle_state::{Clock, InhibitIdleState, InhibitIdleStateEvent};
 
 mod pipewire_connection;
 use message_queue::MessageQueueReceiver;
-use pipewire_connection::{PWEvent, PWMsg, PWThread};
+use pipewire_connection::{
+    PWError, PWEvent, PWMsg, PWThread, graph::filter::Preset, trace::TraceRecorder,
+};
 
 mod idle_inhibitor;
 use idle_inhibitor::{
@@ -47,6 +50,10 @@ use settings::Settings;
 
 mod message_queue;
 
+mod replay;
+
+mod schedule;
+
 use nix::{errno::Errno, sys::epoll::*};
 
 #[repr(u64)]
@@ -66,6 +73,37 @@ impl From<u64> for MessageQueueType {
     }
 }
 
+/// Flags set by signal handlers and checked by the main loops
+struct SignalFlags {
+    term: Arc<AtomicBool>,
+    rearm: Arc<AtomicBool>,
+}
+
+impl SignalFlags {
+    /// Registers the signal handlers. Termination signals stop the main loop, while SIGUSR1
+    /// re-arms the idle inhibitor after the maximum duration was reached.
+    fn new() -> Result<Self, Box<dyn Error>> {
+        let term = Arc::new(AtomicBool::new(false));
+        for sig in signal_hook::consts::TERM_SIGNALS {
+            signal_hook::flag::register(*sig, Arc::clone(&term))?;
+        }
+
+        let rearm = Arc::new(AtomicBool::new(false));
+        signal_hook::flag::register(signal_hook::consts::SIGUSR1, Arc::clone(&rearm))?;
+
+        Ok(Self { term, rearm })
+    }
+
+    fn should_terminate(&self) -> bool {
+        self.term.load(atomic::Ordering::Relaxed)
+    }
+
+    /// Returns true if a re-arm was requested since the last call
+    fn take_rearm(&self) -> bool {
+        self.rearm.swap(false, atomic::Ordering::Relaxed)
+    }
+}
+
 #[derive(Clone, Debug)]
 enum Msg {
     PWEvent(PWEvent),
@@ -75,26 +113,21 @@ enum Msg {
 impl Msg {
     fn handle(
         &self,
-        pw_thread: &PWThread,
         inhibit_idle_state_manager: &mut InhibitIdleState<Msg>,
         idle_inhibitor: &mut dyn IdleInhibitor,
     ) -> Result<(), Box<dyn Error>> {
         match self {
             Msg::PWEvent(pw_event) => match pw_event {
-                PWEvent::GraphUpdated => {
-                    pw_thread.send(PWMsg::GraphUpdated)?;
+                PWEvent::ActiveNodes(remote, active_nodes) => {
+                    inhibit_idle_state_manager.set_active_nodes(*remote, active_nodes.clone());
                 }
 
-                PWEvent::InhibitIdleState(inhibit_idle_state) => {
-                    inhibit_idle_state_manager.set_is_idle_inhibited(*inhibit_idle_state);
+                PWEvent::Disconnected(remote) => {
+                    inhibit_idle_state_manager.disconnected(*remote);
                 }
 
-                PWEvent::ThreadPanic(err) => {
-                    if let Some(err) = err {
-                        Err(format!("Fatal PipeWire Error: {err}"))?;
-                    } else {
-                        Err("Fatal PipeWire Error!")?;
-                    }
+                PWEvent::Error(err) => {
+                    Err(format!("Fatal PipeWire Error: {err}"))?;
                 }
             },
 
@@ -103,6 +136,14 @@ impl Msg {
                     InhibitIdleStateEvent::InhibitIdle(inhibit_idle_state) => {
                         idle_inhibitor.set_inhibit_idle(*inhibit_idle_state)?;
                     }
+
+                    InhibitIdleStateEvent::ActivePaths(active_paths) => {
+                        idle_inhibitor.set_active_paths(active_paths)?;
+                    }
+
+                    InhibitIdleStateEvent::TimerExpired => {
+                        inhibit_idle_state_manager.timer_expired();
+                    }
                 }
             }
         }
@@ -122,6 +163,16 @@ impl From<InhibitIdleStateEvent> for Msg {
     }
 }
 
+/// Prints the built-in node blacklist presets and the filters they expand to
+fn list_presets() {
+    for preset in Preset::ALL {
+        println!("{preset}: {}", preset.description());
+        for filter in preset.filters() {
+            println!("    {filter}");
+        }
+    }
+}
+
 fn main() -> ExitCode {
     match run() {
         Ok(()) => ExitCode::SUCCESS,
@@ -135,6 +186,11 @@ fn main() -> ExitCode {
 fn run() -> Result<(), Box<dyn Error>> {
     let settings = Settings::new()?;
 
+    if settings.get_list_presets() {
+        list_presets();
+        return Ok(());
+    }
+
     simplelog::TermLogger::init(
         settings.get_verbosity(),
         simplelog::Config::default(),
@@ -142,6 +198,10 @@ fn run() -> Result<(), Box<dyn Error>> {
         simplelog::ColorChoice::Auto,
     )?;
 
+    if let Some(trace) = settings.get_replay() {
+        return replay::replay(&settings, trace);
+    }
+
     let epoll = Epoll::new(EpollCreateFlags::empty())?;
     let (mq, mq_receiver) =
         message_queue::message_queue::<Msg>(&epoll, MessageQueueType::Main as u64)?;
@@ -149,7 +209,7 @@ fn run() -> Result<(), Box<dyn Error>> {
     panic::set_hook(Box::new({
         let mq = mq.clone();
         move |panic_info| {
-            let err = panic_info
+            let message = panic_info
                 .payload()
                 .downcast_ref::<&str>()
                 .map(|s| String::from(*s))
@@ -157,34 +217,89 @@ fn run() -> Result<(), Box<dyn Error>> {
                     .payload()
                     .downcast_ref::<String>()
                     .map(|s| s.to_owned()));
+            let err = PWError::Panic {
+                thread: thread::current().name().map(String::from),
+                message,
+            };
 
-            mq.send(Msg::PWEvent(PWEvent::ThreadPanic(err))).unwrap();
+            // Fails if the main thread is no longer listening, in which case the panic is only
+            // logged
+            if let Err(send_err) = mq.send(Msg::PWEvent(PWEvent::Error(err.clone()))) {
+                log::error!(target: "main", "{err} (failed to report it: {send_err})");
+            }
         }
     }));
 
-    let pw_thread = PWThread::new(
+    let graph_config = settings.get_graph_config();
+
+    let recorder = match settings.get_record() {
+        Some(path) => {
+            let recorder = TraceRecorder::create(path)
+                .map_err(|err| format!("Failed to create trace '{path}': {err}"))?;
+            log::info!(target: "main", "Recording PipeWire events to '{path}'");
+            Some(recorder)
+        }
+        None => None,
+    };
+
+    // Each remote is watched by its own thread. Without any configured remote, the default one is
+    // used
+    let remote_names: Vec<Option<String>> = if settings.get_pipewire_remotes().is_empty() {
+        vec![None]
+    } else {
+        settings
+            .get_pipewire_remotes()
+            .iter()
+            .cloned()
+            .map(Some)
+            .collect()
+    };
+    let pw_threads: Vec<PWThread> = remote_names
+        .into_iter()
+        .enumerate()
+        .map(|(remote_id, remote_name)| {
+            PWThread::new(
+                remote_id,
+                remote_name,
+                mq.clone(),
+                graph_config.clone(),
+                recorder.clone(),
+            )
+        })
+        .collect();
+
+    let inhibit_idle_state_manager: InhibitIdleState<Msg> = InhibitIdleState::new(
+        settings.get_media_minimum_duration(),
+        settings.get_media_release_delay(),
+        settings.get_minimum_toggle_interval(),
+        settings.get_media_maximum_duration(),
+        settings.get_schedule().clone(),
+        Clock::system(),
         mq.clone(),
-        settings.get_sink_whitelist().to_vec(),
-        settings.get_node_blacklist().to_vec(),
     );
 
-    let inhibit_idle_state_manager: InhibitIdleState<Msg> =
-        InhibitIdleState::new(settings.get_media_minimum_duration(), mq.clone());
+    let signal_flags = SignalFlags::new()?;
 
-    let term = Arc::new(AtomicBool::new(false));
-    for sig in signal_hook::consts::TERM_SIGNALS {
-        signal_hook::flag::register(*sig, Arc::clone(&term))?;
+    if settings.get_rearm_on_user_input()
+        && !matches!(
+            settings.get_idle_inhibitor(),
+            settings::IdleInhibitor::Wayland
+        )
+    {
+        log::warn!(target: "main", "Re-arming on user input is only supported by the Wayland idle inhibitor");
     }
 
     match settings.get_idle_inhibitor() {
         settings::IdleInhibitor::DBus => {
-            let idle_inhibitor = Box::new(DbusIdleInhibitor::new()?);
+            let idle_inhibitor = Box::new(DbusIdleInhibitor::new(
+                settings.get_dbus_reason(),
+                settings.get_dbus_inhibit_per_app(),
+            )?);
             non_wayland_main_loop(
                 idle_inhibitor,
-                term,
+                &signal_flags,
                 epoll,
                 mq_receiver,
-                &pw_thread,
                 inhibit_idle_state_manager,
             )?;
         }
@@ -192,29 +307,32 @@ fn run() -> Result<(), Box<dyn Error>> {
             let idle_inhibitor = Box::<DryRunIdleInhibitor>::default();
             non_wayland_main_loop(
                 idle_inhibitor,
-                term,
+                &signal_flags,
                 epoll,
                 mq_receiver,
-                &pw_thread,
                 inhibit_idle_state_manager,
             )?;
         }
         settings::IdleInhibitor::Wayland => {
-            let (idle_inhibitor, event_queue) = WaylandIdleInhibitor::new()?;
+            let (idle_inhibitor, event_queue) =
+                WaylandIdleInhibitor::new(settings.get_rearm_on_user_input())?;
             wayland_main_loop(
                 idle_inhibitor,
                 event_queue,
-                term,
+                &signal_flags,
                 epoll,
                 mq_receiver,
-                &pw_thread,
                 inhibit_idle_state_manager,
             )?;
         }
     };
 
-    pw_thread.send(PWMsg::Terminate)?;
-    pw_thread.join()?;
+    for pw_thread in &pw_threads {
+        pw_thread.send(PWMsg::Terminate)?;
+    }
+    for pw_thread in pw_threads {
+        pw_thread.join()?;
+    }
 
     Ok(())
 }
@@ -222,13 +340,16 @@ fn run() -> Result<(), Box<dyn Error>> {
 fn wayland_main_loop(
     mut wayland_idle_inhibitor: WaylandIdleInhibitor,
     mut wayland_event_queue: WaylandEventQueue,
-    term: Arc<AtomicBool>,
+    signal_flags: &SignalFlags,
     epoll: Epoll,
     mq_receiver: MessageQueueReceiver<Msg>,
-    pw_thread: &PWThread,
     mut inhibit_idle_state_manager: InhibitIdleState<Msg>,
 ) -> Result<(), Box<dyn Error>> {
-    while !term.load(atomic::Ordering::Relaxed) {
+    while !signal_flags.should_terminate() {
+        if signal_flags.take_rearm() {
+            inhibit_idle_state_manager.rearm();
+        }
+
         wayland_event_queue.flush()?;
         let wayland_read_guard =
             if let Some(wayland_read_guard) = wayland_event_queue.prepare_read() {
@@ -259,17 +380,18 @@ fn wayland_main_loop(
         match event.data().into() {
             MessageQueueType::Main => {
                 std::mem::drop(wayland_read_guard);
-                mq_receiver.recv()?.handle(
-                    pw_thread,
-                    &mut inhibit_idle_state_manager,
-                    &mut wayland_idle_inhibitor,
-                )?;
+                mq_receiver
+                    .recv()?
+                    .handle(&mut inhibit_idle_state_manager, &mut wayland_idle_inhibitor)?;
             }
 
             MessageQueueType::Wayland => {
                 if wayland_read_guard.read().is_ok() {
                     wayland_event_queue.dispatch_pending(&mut wayland_idle_inhibitor)?;
                 }
+                if wayland_idle_inhibitor.take_user_activity() {
+                    inhibit_idle_state_manager.rearm();
+                }
             }
 
             MessageQueueType::Unknown => log::error!(target: "main", "Unknown event queue"),
@@ -280,13 +402,16 @@ fn wayland_main_loop(
 
 fn non_wayland_main_loop(
     mut idle_inhibitor: Box<dyn IdleInhibitor>,
-    term: Arc<AtomicBool>,
+    signal_flags: &SignalFlags,
     epoll: Epoll,
     mq_receiver: MessageQueueReceiver<Msg>,
-    pw_thread: &PWThread,
     mut inhibit_idle_state_manager: InhibitIdleState<Msg>,
 ) -> Result<(), Box<dyn Error>> {
-    while !term.load(atomic::Ordering::Relaxed) {
+    while !signal_flags.should_terminate() {
+        if signal_flags.take_rearm() {
+            inhibit_idle_state_manager.rearm();
+        }
+
         let mut events = [EpollEvent::empty()];
         let event = match epoll.wait(&mut events, EpollTimeout::NONE) {
             Ok(_) => events[0],
@@ -295,11 +420,9 @@ fn non_wayland_main_loop(
         };
 
         match event.data().into() {
-            MessageQueueType::Main => mq_receiver.recv()?.handle(
-                pw_thread,
-                &mut inhibit_idle_state_manager,
-                idle_inhibitor.as_mut(),
-            )?,
+            MessageQueueType::Main => mq_receiver
+                .recv()?
+                .handle(&mut inhibit_idle_state_manager, idle_inhibitor.as_mut())?,
 
             MessageQueueType::Unknown => log::error!(target: "main", "Unknown event queue"),
 
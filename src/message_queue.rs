@@ -1,4 +1,4 @@
-// Copyright (C) 2025  Rafael Carvalho <contact@rafaelrc.com>
+// Copyright (C) 2025-2026  Rafael Carvalho <contact@rafaelrc.com>
 
 // This program is free software: you can redistribute it and/or modify
 // it under the terms of the GNU General Public License version 3 as published by
@@ -72,4 +72,16 @@ impl<T: Clone> MessageQueueReceiver<T> {
         self.eventfd.read()?;
         Ok(self.receiver.recv()?)
     }
+
+    /// Receives a message if one is queued, without blocking
+    pub fn try_recv(&self) -> Result<Option<T>, Box<dyn Error>> {
+        match self.receiver.try_recv() {
+            Ok(payload) => {
+                self.eventfd.read()?;
+                Ok(Some(payload))
+            }
+            Err(mpsc::TryRecvError::Empty) => Ok(None),
+            Err(err) => Err(err)?,
+        }
+    }
 }
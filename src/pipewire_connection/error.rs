@@ -0,0 +1,110 @@
+// Copyright (C) 2026  Rafael Carvalho <contact@rafaelrc.com>
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Errors of the PipeWire thread.
+//!
+//! Errors caused by a single object, such as an app announcing a node without properties, are
+//! logged and the object is skipped. Only fatal errors stop the thread, and are sent to the caller
+//! as a [super::PWEvent::Error].
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+use pipewire::{registry::GlobalObject, spa::utils::dict::DictRef, types::ObjectType};
+
+use super::graph::object::Id;
+
+#[derive(Clone, Debug)]
+pub enum PWError {
+    /// An object was sent by the PipeWire server without properties
+    MissingProperties { object_type: ObjectType, id: Id },
+    /// A proxy to an object could not be bound
+    Bind {
+        object_type: ObjectType,
+        id: Id,
+        source: Arc<pipewire::Error>,
+    },
+    /// The [pipewire::main_loop::MainLoop] could not be created
+    MainLoop(Arc<pipewire::Error>),
+    /// The [pipewire::context::Context] could not be created
+    Context(Arc<pipewire::Error>),
+    /// The caller is no longer listening to events
+    ChannelClosed,
+    /// A thread panicked, with the name of the thread and the panic message, if any
+    Panic {
+        thread: Option<String>,
+        message: Option<String>,
+    },
+}
+
+impl PWError {
+    pub fn missing_properties<P: AsRef<DictRef>>(global: &GlobalObject<P>) -> Self {
+        Self::MissingProperties {
+            object_type: global.type_.clone(),
+            id: global.id,
+        }
+    }
+
+    pub fn bind<P: AsRef<DictRef>>(global: &GlobalObject<P>, source: pipewire::Error) -> Self {
+        Self::Bind {
+            object_type: global.type_.clone(),
+            id: global.id,
+            source: Arc::new(source),
+        }
+    }
+}
+
+impl Display for PWError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingProperties { object_type, id } => {
+                write!(f, "{object_type:?} object {id} is missing properties")
+            }
+            Self::Bind {
+                object_type,
+                id,
+                source,
+            } => write!(f, "Failed to bind {object_type:?} proxy {id}: {source}"),
+            Self::MainLoop(source) => write!(f, "Failed to create mainloop: {source}"),
+            Self::Context(source) => write!(f, "Failed to create context: {source}"),
+            Self::ChannelClosed => write!(f, "Event channel of the PipeWire thread was closed"),
+            Self::Panic { thread, message } => {
+                match thread {
+                    Some(thread) => write!(f, "Thread '{thread}' panicked")?,
+                    None => write!(f, "Unnamed thread panicked")?,
+                }
+                match message {
+                    Some(message) => write!(f, ": {message}"),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl Error for PWError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Bind { source, .. } | Self::MainLoop(source) | Self::Context(source) => {
+                Some(source.as_ref())
+            }
+            _ => None,
+        }
+    }
+}
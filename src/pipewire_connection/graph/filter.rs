@@ -1,4 +1,4 @@
-// Copyright (C) 2024  Rafael Carvalho <contact@rafaelrc.com>
+// Copyright (C) 2024-2026  Rafael Carvalho <contact@rafaelrc.com>
 
 // This program is free software: you can redistribute it and/or modify
 // it under the terms of the GNU General Public License version 3 as published by
@@ -16,10 +16,12 @@
 
 //! Tools used for filtering over [super::PWGraph] objects.
 
+use std::fmt::Display;
+
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use super::NodeData;
+use super::{ClientData, DeviceData, NodeData};
 
 /// Represents a generic filter for a generic type. In the contexts of this application, it is used
 /// to filter objects of the [super::PWGraph], mainly [super::NodeData]s.
@@ -69,22 +71,70 @@ fn matches_property(filter: &Option<Regex>, property: Option<&str>) -> bool {
         .is_none_or(|f| property.is_some_and(|p| f.is_match(p)))
 }
 
+/// A node together with the objects it is associated to in the graph, used to filter nodes by the
+/// properties of, for example, the client that created them or the device they belong to.
+#[derive(Clone, Copy)]
+pub struct NodeContext<'a> {
+    pub node: &'a NodeData,
+    pub client: Option<&'a ClientData>,
+    pub device: Option<&'a DeviceData>,
+    pub is_default_sink: bool,
+}
+
 /// Represents a [Filter] over a Sink. A Sink is a special case of a Node, and thus filters over
-/// [super::NodeData]s.
-#[derive(Serialize, Deserialize, Clone)]
+/// [NodeContext]s, matching also properties of the Sink's device.
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct SinkFilter {
+    #[serde(default)]
+    default: Option<bool>,
+
     #[serde(default, with = "serde_regex")]
     name: Option<Regex>,
+
+    #[serde(default, with = "serde_regex")]
+    device_name: Option<Regex>,
+
+    #[serde(default, with = "serde_regex")]
+    device_description: Option<Regex>,
+
+    #[serde(default, with = "serde_regex")]
+    device_api: Option<Regex>,
+
+    #[serde(default, with = "serde_regex")]
+    device_bus: Option<Regex>,
+
+    #[serde(default, with = "serde_regex")]
+    device_form_factor: Option<Regex>,
 }
 
-impl Filter<NodeData> for SinkFilter {
-    fn matches(&self, node: &NodeData) -> bool {
-        matches_property(&self.name, node.get_name())
+impl Filter<NodeContext<'_>> for SinkFilter {
+    fn matches(&self, context: &NodeContext) -> bool {
+        let NodeContext {
+            node,
+            device,
+            is_default_sink,
+            ..
+        } = context;
+
+        self.default
+            .is_none_or(|default| default == *is_default_sink)
+            && matches_property(&self.name, node.get_name())
+            && matches_property(&self.device_name, device.and_then(|d| d.name.as_deref()))
+            && matches_property(
+                &self.device_description,
+                device.and_then(|d| d.description.as_deref()),
+            )
+            && matches_property(&self.device_api, device.and_then(|d| d.api.as_deref()))
+            && matches_property(&self.device_bus, device.and_then(|d| d.bus.as_deref()))
+            && matches_property(
+                &self.device_form_factor,
+                device.and_then(|d| d.form_factor.as_deref()),
+            )
     }
 }
 
-/// Represents a [Filter] over a generic Node, and thus filters over [super::NodeData]s.
-#[derive(Serialize, Deserialize, Clone)]
+/// Represents a [Filter] over a generic Node, and thus filters over [NodeContext]s.
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct NodeFilter {
     #[serde(default, with = "serde_regex")]
     name: Option<Regex>,
@@ -100,14 +150,163 @@ pub struct NodeFilter {
 
     #[serde(default, with = "serde_regex")]
     media_software: Option<Regex>,
+
+    #[serde(default, with = "serde_regex")]
+    process_binary: Option<Regex>,
+
+    #[serde(default, with = "serde_regex")]
+    app_id: Option<Regex>,
 }
 
-impl Filter<NodeData> for NodeFilter {
-    fn matches(&self, node: &NodeData) -> bool {
+impl Filter<NodeContext<'_>> for NodeFilter {
+    /// The `app_name` filter matches either the node or its client application name, as some
+    /// streams don't set it themselves.
+    fn matches(&self, context: &NodeContext) -> bool {
+        let NodeContext { node, client, .. } = context;
+        let client_app_name = client.and_then(|c| c.app_name.as_deref());
+
         matches_property(&self.name, node.get_name())
-            && matches_property(&self.app_name, node.app_name.as_deref())
+            && (matches_property(&self.app_name, node.app_name.as_deref())
+                || matches_property(&self.app_name, client_app_name))
             && matches_property(&self.media_class, node.media_class.as_deref())
             && matches_property(&self.media_role, node.media_role.as_deref())
             && matches_property(&self.media_software, node.media_software.as_deref())
+            && matches_property(
+                &self.process_binary,
+                client.and_then(|c| c.process_binary.as_deref()),
+            )
+            && matches_property(&self.app_id, client.and_then(|c| c.app_id.as_deref()))
     }
 }
+
+/// Displays the filter in the same format used in the config file, with only the set fields, e.g.
+/// `{ media_role = "^Notification$" }`
+impl Display for NodeFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = [
+            ("name", &self.name),
+            ("app_name", &self.app_name),
+            ("media_class", &self.media_class),
+            ("media_role", &self.media_role),
+            ("media_software", &self.media_software),
+            ("process_binary", &self.process_binary),
+            ("app_id", &self.app_id),
+        ];
+
+        f.write_str("{")?;
+        let mut separator = " ";
+        for (field, filter) in fields {
+            if let Some(filter) = filter {
+                write!(f, "{separator}{field} = {:?}", filter.as_str())?;
+                separator = ", ";
+            }
+        }
+        f.write_str(" }")
+    }
+}
+
+/// A path taken by media in the graph: a node that is (directly or indirectly) connected to the
+/// sink at the root of the current walk.
+pub struct Route<'a> {
+    pub node: NodeContext<'a>,
+    pub sink: NodeContext<'a>,
+}
+
+/// Represents a [Filter] over a [Route], matching both the node and the sink it is connected to.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RouteFilter {
+    #[serde(default)]
+    node: NodeFilter,
+
+    #[serde(default)]
+    sink: SinkFilter,
+}
+
+impl Filter<Route<'_>> for RouteFilter {
+    fn matches(&self, route: &Route) -> bool {
+        self.node.matches(&route.node) && self.sink.matches(&route.sink)
+    }
+}
+
+/// Named, built-in lists of [NodeFilter]s for commonly ignored kinds of streams, that may be added
+/// to the node blacklist.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    Notifications,
+    EventSounds,
+    ScreenReader,
+    RingTones,
+}
+
+impl Preset {
+    /// All available presets
+    pub const ALL: [Self; 4] = [
+        Self::Notifications,
+        Self::EventSounds,
+        Self::ScreenReader,
+        Self::RingTones,
+    ];
+
+    /// Short description of the streams matched by the preset
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Notifications => "Notification and event sounds, by media role",
+            Self::EventSounds => "System event sounds played through libcanberra",
+            Self::ScreenReader => "Screen readers and text to speech, such as speech-dispatcher",
+            Self::RingTones => "Ring tones and call alerts, but not calls, by media role",
+        }
+    }
+
+    /// Expands the preset into the [NodeFilter]s it stands for
+    pub fn filters(&self) -> Vec<NodeFilter> {
+        match self {
+            Self::Notifications => vec![NodeFilter {
+                media_role: preset_regex("^(Notification|Event|event)$"),
+                ..Default::default()
+            }],
+            Self::EventSounds => vec![
+                NodeFilter {
+                    app_name: preset_regex("^libcanberra$"),
+                    ..Default::default()
+                },
+                NodeFilter {
+                    process_binary: preset_regex("^canberra-gtk-play$"),
+                    ..Default::default()
+                },
+            ],
+            Self::ScreenReader => vec![
+                NodeFilter {
+                    app_name: preset_regex("^speech-dispatcher"),
+                    ..Default::default()
+                },
+                NodeFilter {
+                    media_role: preset_regex("^(Accessibility|a11y)$"),
+                    ..Default::default()
+                },
+            ],
+            // Calls use the Communication or phone roles, and are not matched, so that idle stays
+            // inhibited during them
+            Self::RingTones => vec![NodeFilter {
+                media_role: preset_regex("^(Alert|Ringtone|Notification)$"),
+                ..Default::default()
+            }],
+        }
+    }
+}
+
+impl Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Notifications => f.write_str("notifications"),
+            Self::EventSounds => f.write_str("event-sounds"),
+            Self::ScreenReader => f.write_str("screen-reader"),
+            Self::RingTones => f.write_str("ring-tones"),
+        }
+    }
+}
+
+/// Builds the [Regex] of a preset filter. Preset patterns are constant, and thus always valid.
+fn preset_regex(pattern: &str) -> Option<Regex> {
+    Some(Regex::new(pattern).expect("Invalid preset regex"))
+}
@@ -1,4 +1,4 @@
-// Copyright (C) 2023-2024  Rafael Carvalho <contact@rafaelrc.com>
+// Copyright (C) 2023-2026  Rafael Carvalho <contact@rafaelrc.com>
 
 // This program is free software: you can redistribute it and/or modify
 // it under the terms of the GNU General Public License version 3 as published by
@@ -15,71 +15,183 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 //! Module responsible to represent and treat the PipeWire Graph, in the context of this app,
-//! composed of [PWObject]s, that can be Nodes, Links or Ports.
+//! composed of [PWObject]s, that can be Nodes, Links, Ports, Clients or Devices.
 
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
 
 use log::{debug, trace, warn};
-use pipewire::spa::utils::Direction;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 pub mod filter;
-use filter::{Filter, NodeFilter, SinkFilter};
+use filter::{Filter, NodeContext, NodeFilter, Route, RouteFilter, SinkFilter};
 
 pub mod object;
-use object::{Id, LinkData, NodeData, PWObject, PWObjectData, PortData};
+use object::{
+    ClientData, DeviceData, Direction, Id, LinkData, NodeData, PWObject, PWObjectData, PortData,
+    Serial,
+};
+
+/// Which of the default sinks announced by the PipeWire metadata is updated by
+/// [PWGraph::set_default_sink]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultSinkKey {
+    /// The default sink currently in use
+    Default,
+    /// The default sink configured by the user
+    Configured,
+    /// All default sinks, used when they are cleared
+    All,
+}
+
+/// A client node found to be playing media into a watched sink
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActiveNode {
+    /// True if the node matches the node or route blacklists, or is only connected to watched sinks
+    /// through nodes that match them
+    pub is_blacklisted: bool,
+    /// The paths through which the node reaches watched sinks
+    pub paths: Vec<ActivePath>,
+}
+
+/// A path of active links from a client node to a watched sink, going through intermediate nodes
+/// such as filters and virtual sinks. Nodes are stored by their names, from the client to the sink.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ActivePath {
+    pub nodes: Vec<String>,
+    /// True if the path goes through nodes that match the node or route blacklists
+    pub is_blacklisted: bool,
+}
+
+impl ActivePath {
+    /// Name of the client node at the start of the path
+    pub fn client(&self) -> Option<&str> {
+        self.nodes.first().map(String::as_str)
+    }
+
+    /// Name of the sink at the end of the path
+    pub fn sink(&self) -> Option<&str> {
+        self.nodes.last().map(String::as_str)
+    }
+}
+
+impl Display for ActivePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.nodes.join(" → "))?;
+        if self.is_blacklisted {
+            write!(f, " (blacklisted)")?;
+        }
+        Ok(())
+    }
+}
+
+/// User defined options that change how the [PWGraph] is transversed
+#[derive(Clone)]
+pub struct GraphConfig {
+    pub sink_whitelist: Vec<SinkFilter>,
+    pub node_blacklist: Vec<NodeFilter>,
+    pub route_blacklist: Vec<RouteFilter>,
+    /// Media classes of the nodes that are counted as playing clients
+    pub client_media_classes: Vec<Regex>,
+    /// Count nodes recording the monitor of watched sinks as playing clients
+    pub detect_recording: bool,
+    pub recorder_whitelist: Vec<NodeFilter>,
+    /// Ignore passive links and links to or from passive nodes
+    pub ignore_passive: bool,
+    /// Ignore paths through muted nodes
+    pub ignore_muted: bool,
+    /// Ignore paths through nodes with a volume, in percent, below this value
+    pub minimum_volume: Option<f32>,
+}
+
+/// Result of the walk from a single watched sink. It is cached until any of the nodes reached by
+/// the walk, or the objects attached to them, change.
+#[derive(Default)]
+struct SinkActivity {
+    active_nodes: HashMap<Id, ActiveNode>,
+    /// Every node looked at during the walk, including the ones where it stopped
+    reached_nodes: HashSet<Id>,
+}
 
 /// Struct that represents the [pipewire] graph.
 ///
 /// Tracked objects are store in a [HashMap] with its id used as key
 ///
-/// Fast access to links attached to ports and the port's nodes are also kept in maps.
+/// As ids are reused by the PipeWire server, the `object.serial` of each object is also kept, so
+/// that events of an older object with the same id are ignored.
+///
+/// Fast access to links attached to ports, the port's nodes and the nodes owned by each client or
+/// device are also kept in maps.
+///
+/// The activity of each watched sink is cached, and only recomputed when objects reached by its
+/// walk are inserted, updated or removed. The active nodes of all sinks are kept merged, and only
+/// the entries of the nodes found by dropped or recomputed sinks are merged again.
 pub struct PWGraph {
     objects: HashMap<Id, PWObject>,
+    serials: HashMap<Id, Serial>,
     sinks: HashSet<Id>,
     links_to_port: HashMap<Id, HashSet<Id>>,
     links_from_port: HashMap<Id, HashSet<Id>>,
     node_input_ports: HashMap<Id, HashSet<Id>>,
     node_output_ports: HashMap<Id, HashSet<Id>>,
-    sink_whitelist: Vec<SinkFilter>,
-    node_blacklist: Vec<NodeFilter>,
+    /// Nodes by the id of the client that created them or the device they belong to
+    owned_nodes: HashMap<Id, HashSet<Id>>,
+    config: GraphConfig,
+    default_sink: Option<String>,
+    configured_default_sink: Option<String>,
+    sink_activity: HashMap<Id, SinkActivity>,
+    active_nodes: HashMap<Id, ActiveNode>,
+    /// Nodes whose entry in `active_nodes` must be merged again from the activity of every sink
+    changed_nodes: HashSet<Id>,
 }
 
 impl PWGraph {
     /// Builds a new [PWGraph]
     ///
-    /// The vectors of [SinkFilter]s and [NodeFilter]s are defined by the user and, thus, are
-    /// passed as arguments.
-    pub fn new(sink_whitelist: Vec<SinkFilter>, node_blacklist: Vec<NodeFilter>) -> Self {
+    /// The [GraphConfig], with the [SinkFilter]s, [NodeFilter]s and [RouteFilter]s, is defined by
+    /// the user and, thus, is passed as argument.
+    pub fn new(config: GraphConfig) -> Self {
         Self {
             objects: HashMap::default(),
+            serials: HashMap::default(),
             sinks: HashSet::default(),
             links_to_port: HashMap::default(),
             links_from_port: HashMap::default(),
             node_input_ports: HashMap::default(),
             node_output_ports: HashMap::default(),
-            sink_whitelist,
-            node_blacklist,
+            owned_nodes: HashMap::default(),
+            config,
+            default_sink: None,
+            configured_default_sink: None,
+            sink_activity: HashMap::default(),
+            active_nodes: HashMap::default(),
+            changed_nodes: HashSet::default(),
         }
     }
 
-    /// Inserts a new object into the Graph.
+    /// Inserts a new object into the Graph, with its `object.serial`, if known.
     ///
-    /// Currently ID conflicts are not treated.
-    pub fn insert(&mut self, id: Id, obj: PWObject) {
+    /// If an object with the same id is already in the graph, its removal was missed, and it is
+    /// removed first, along with every reference to it.
+    pub fn insert(&mut self, id: Id, serial: Option<Serial>, obj: PWObject) {
+        if self.objects.contains_key(&id) {
+            warn!(target: "PWGraph::insert", "Object ({id}) replaces an older object with the same id");
+            self.remove(id);
+        }
+        if let Some(serial) = serial {
+            self.serials.insert(id, serial);
+        }
+
         match obj {
             PWObject::Node { ref data, .. } => {
-                let NodeData {
-                    ref media_class, ..
-                } = data;
                 debug!(target: "PWGraph::insert", "Node ({id}) '{}'; {:?}", data.get_name().unwrap_or_default(), data);
-                if let Some(media_class) = media_class {
-                    if media_class.contains("Sink")
-                        && (self.sink_whitelist.is_empty()
-                            || SinkFilter::matches_any(&self.sink_whitelist, data))
-                    {
-                        self.sinks.insert(id);
-                    }
-                };
+                for owner in [data.client_id, data.device_id].into_iter().flatten() {
+                    self.owned_nodes.entry(owner).or_default().insert(id);
+                }
             }
             PWObject::Port { ref data, .. } => {
                 let PortData {
@@ -102,7 +214,6 @@ impl PWGraph {
                                 .or_default()
                                 .insert(id);
                         }
-                        _ => {}
                     };
                 };
             }
@@ -131,113 +242,68 @@ impl PWGraph {
                         .insert(id);
                 };
             }
+            PWObject::Client { ref data, .. } => {
+                debug!(target: "PWGraph::insert", "Client ({id}); {data:?}");
+            }
+            PWObject::Device { ref data, .. } => {
+                debug!(target: "PWGraph::insert", "Device ({id}); {data:?}");
+            }
+            PWObject::Metadata => {
+                debug!(target: "PWGraph::insert", "Metadata ({id})");
+            }
         }
 
+        let affects_sinks = matches!(obj, PWObject::Node { .. } | PWObject::Device { .. });
         self.objects.insert(id, obj);
+
+        let affected_nodes = self.get_affected_nodes(id);
+        if affects_sinks {
+            self.update_sinks(&affected_nodes);
+        }
+        self.invalidate_nodes(&affected_nodes);
     }
 
-    /// Updates an object data
-    pub fn update(&mut self, id: Id, new_data: PWObjectData) -> bool {
+    /// Updates an object data. The update is ignored if its `object.serial` is known and belongs
+    /// to an older object with the same id.
+    pub fn update(&mut self, id: Id, serial: Option<Serial>, new_data: PWObjectData) -> bool {
         trace!(target: "PWGraph::update", "Called for object with ID {id}");
+        if let (Some(serial), Some(current_serial)) = (serial, self.serials.get(&id)) {
+            if serial != *current_serial {
+                debug!(target: "PWGraph::update", "Ignored update of older object ({id}) with serial {serial}");
+                return false;
+            }
+        }
+        let mut affected_nodes = self.get_affected_nodes(id);
         let Some(obj) = self.objects.get_mut(&id) else {
             warn!(target: "PWGraph::update", "Tried to update inexistent object with ID {id}");
             return false;
         };
 
-        match new_data {
+        let affects_sinks = matches!(new_data, PWObjectData::Node(_) | PWObjectData::Device(_));
+        let was_updated = match new_data {
             PWObjectData::Node(new_data) => {
                 let PWObject::Node { ref mut data, .. } = obj else {
                     warn!(target: "PWGraph::update", "Tried to update Node, but object of ID {id} is not a Node");
                     return false;
                 };
 
-                let NodeData {
-                    media_class: ref new_media_class,
-                    ..
-                } = new_data;
-
-                let NodeData {
-                    ref media_class, ..
-                } = data;
-
-                if media_class != new_media_class {
-                    if let Some(new_media_class) = new_media_class {
-                        if let Some(media_class) = media_class {
-                            if media_class.contains("Sink") {
-                                self.sinks.remove(&id);
-                            }
-                        }
-                        if new_media_class.contains("Sink")
-                            && (self.sink_whitelist.is_empty()
-                                || SinkFilter::matches_any(&self.sink_whitelist, &new_data))
-                        {
-                            self.sinks.insert(id);
-                        }
-                    }
-                }
-
                 debug!(target: "PWGraph::update", "Updated Node ({id}) from {data:?}");
+                let owners = [data.client_id, data.device_id];
                 let was_updated = data.update(new_data);
                 debug!(target: "PWGraph::update", "Updated Node ({id}) to {data:?}");
-                was_updated
-            }
-            PWObjectData::Port(new_data) => {
-                let PWObject::Port { ref mut data, .. } = obj else {
-                    warn!(target: "PWGraph::update", "Tried to update Port, but object of ID {id} is not a Port");
-                    return false;
-                };
-
-                let PortData {
-                    node_id: ref new_node_id,
-                    direction: ref new_direction,
-                    ..
-                } = new_data;
-                let PortData {
-                    ref node_id,
-                    ref direction,
-                    ..
-                } = data;
 
-                if node_id != new_node_id || direction != new_direction {
-                    if let (Some(new_node_id), Some(new_direction)) = (new_node_id, new_direction) {
-                        if let (Some(node_id), Some(direction)) = (node_id, direction) {
-                            match *direction {
-                                Direction::Input => {
-                                    self.node_input_ports
-                                        .entry(*node_id)
-                                        .or_default()
-                                        .remove(&id);
-                                }
-                                Direction::Output => {
-                                    self.node_output_ports
-                                        .entry(*node_id)
-                                        .or_default()
-                                        .remove(&id);
-                                }
-                                _ => {}
-                            }
-                        }
-                        match *new_direction {
-                            Direction::Input => {
-                                self.node_input_ports
-                                    .entry(*new_node_id)
-                                    .or_default()
-                                    .insert(id);
-                            }
-                            Direction::Output => {
-                                self.node_output_ports
-                                    .entry(*new_node_id)
-                                    .or_default()
-                                    .insert(id);
-                            }
-                            _ => {}
-                        }
+                let new_owners = [data.client_id, data.device_id];
+                for (owner, new_owner) in owners.into_iter().zip(new_owners) {
+                    if owner == new_owner {
+                        continue;
+                    }
+                    if let Some(owner) = owner {
+                        remove_from_index(&mut self.owned_nodes, &owner, id);
+                    }
+                    if let Some(new_owner) = new_owner {
+                        self.owned_nodes.entry(new_owner).or_default().insert(id);
                     }
                 }
-
-                debug!(target: "PWGraph::update", "Updated Port ({id}) from {data:?}");
-                let was_updated = data.update(new_data);
-                debug!(target: "PWGraph::update", "Updated Port ({id}) to {data:?}");
                 was_updated
             }
             PWObjectData::Link(new_data) => {
@@ -260,10 +326,7 @@ impl PWGraph {
                 if output_port != new_output_port {
                     if let Some(new_output_port) = new_output_port {
                         if let Some(output_port) = output_port {
-                            self.links_from_port
-                                .entry(*output_port)
-                                .or_default()
-                                .remove(&id);
+                            remove_from_index(&mut self.links_from_port, output_port, id);
                         }
                         self.links_from_port
                             .entry(*new_output_port)
@@ -275,10 +338,7 @@ impl PWGraph {
                 if input_port != new_input_port {
                     if let Some(new_input_port) = new_input_port {
                         if let Some(input_port) = input_port {
-                            self.links_to_port
-                                .entry(*input_port)
-                                .or_default()
-                                .remove(&id);
+                            remove_from_index(&mut self.links_to_port, input_port, id);
                         }
                         self.links_to_port
                             .entry(*new_input_port)
@@ -292,22 +352,59 @@ impl PWGraph {
                 debug!(target: "PWGraph::update", "Updated Link ({id}) to {data:?}");
                 was_updated
             }
+            PWObjectData::Client(new_data) => {
+                let PWObject::Client { ref mut data, .. } = obj else {
+                    warn!(target: "PWGraph::update", "Tried to update Client, but object of ID {id} is not a Client");
+                    return false;
+                };
+
+                debug!(target: "PWGraph::update", "Updated Client ({id}) from {data:?}");
+                let was_updated = data.update(new_data);
+                debug!(target: "PWGraph::update", "Updated Client ({id}) to {data:?}");
+                was_updated
+            }
+            PWObjectData::Device(new_data) => {
+                let PWObject::Device { ref mut data, .. } = obj else {
+                    warn!(target: "PWGraph::update", "Tried to update Device, but object of ID {id} is not a Device");
+                    return false;
+                };
+
+                debug!(target: "PWGraph::update", "Updated Device ({id}) from {data:?}");
+                let was_updated = data.update(new_data);
+                debug!(target: "PWGraph::update", "Updated Device ({id}) to {data:?}");
+                was_updated
+            }
+        };
+
+        if was_updated {
+            affected_nodes.extend(self.get_affected_nodes(id));
+            if affects_sinks {
+                self.update_sinks(&affected_nodes);
+            }
+            self.invalidate_nodes(&affected_nodes);
         }
+
+        was_updated
     }
 
     /// Remove an object from the graph and cleans up references to it.
     pub fn remove(&mut self, id: Id) -> Option<PWObject> {
         trace!(target: "PWGraph::remove", "Called for object with ID {id}");
+        let affected_nodes = self.get_affected_nodes(id);
         let removed = self.objects.remove(&id);
+        self.serials.remove(&id);
+        self.invalidate_nodes(&affected_nodes);
 
         match removed {
             Some(PWObject::Node { ref data, .. }) => {
-                let NodeData { media_class, .. } = data;
-                if let Some(media_class) = media_class {
-                    if media_class.contains("Sink") {
-                        self.sinks.remove(&id);
-                    }
+                for owner in [data.client_id, data.device_id].into_iter().flatten() {
+                    remove_from_index(&mut self.owned_nodes, &owner, id);
                 }
+                // Ports still attached to the removed node are not ports of a new node with the
+                // same id
+                self.node_input_ports.remove(&id);
+                self.node_output_ports.remove(&id);
+                self.update_sinks(&affected_nodes);
                 debug!(target: "PWGraph::remove", "Removed Node ({id})");
             }
             Some(PWObject::Port { ref data, .. }) => {
@@ -317,20 +414,15 @@ impl PWGraph {
                 if let (Some(node_id), Some(direction)) = (node_id, direction) {
                     match *direction {
                         Direction::Input => {
-                            self.node_input_ports
-                                .entry(*node_id)
-                                .or_default()
-                                .remove(&id);
+                            remove_from_index(&mut self.node_input_ports, node_id, id);
                         }
                         Direction::Output => {
-                            self.node_output_ports
-                                .entry(*node_id)
-                                .or_default()
-                                .remove(&id);
+                            remove_from_index(&mut self.node_output_ports, node_id, id);
                         }
-                        _ => {}
                     };
                 }
+                self.links_to_port.remove(&id);
+                self.links_from_port.remove(&id);
                 debug!(target: "PWGraph::remove", "Removed Port ({id})");
             }
             Some(PWObject::Link { ref data, .. }) => {
@@ -340,20 +432,25 @@ impl PWGraph {
                     ..
                 } = data;
                 if let Some(output_port) = output_port {
-                    self.links_from_port
-                        .entry(*output_port)
-                        .or_default()
-                        .remove(&id);
+                    remove_from_index(&mut self.links_from_port, output_port, id);
                 };
 
                 if let Some(input_port) = input_port {
-                    self.links_to_port
-                        .entry(*input_port)
-                        .or_default()
-                        .remove(&id);
+                    remove_from_index(&mut self.links_to_port, input_port, id);
                 };
                 debug!(target: "PWGraph::remove", "Removed Link ({id})");
             }
+            Some(PWObject::Client { .. }) => {
+                debug!(target: "PWGraph::remove", "Removed Client ({id})");
+            }
+            Some(PWObject::Device { .. }) => {
+                self.update_sinks(&affected_nodes);
+                debug!(target: "PWGraph::remove", "Removed Device ({id})");
+            }
+            Some(PWObject::Metadata) => {
+                self.set_default_sink(DefaultSinkKey::All, None);
+                debug!(target: "PWGraph::remove", "Removed Metadata ({id})");
+            }
             None => {
                 trace!(target: "PWGraph::remove", "Tried to remove inexistent object with ID {id}");
             }
@@ -366,60 +463,403 @@ impl PWGraph {
         self.objects.get(id)
     }
 
-    /// Looks for sinks with active links to tracked nodes.
+    /// Gets the nodes whose changes, or changes to the objects attached to them, may change the
+    /// result of walks that reached them, if an object with the given id is changed. For links, this
+    /// is the node they go into and, as monitor ports are followed to find recorders, the node they
+    /// come from if it is a sink. For ports, this is their node and the nodes their links go into,
+    /// as walks may have stopped at links from ports that were not known yet. For clients and
+    /// devices, these are the nodes they own.
+    fn get_affected_nodes(&self, id: Id) -> Vec<Id> {
+        match self.get(&id) {
+            Some(PWObject::Node { .. }) => vec![id],
+            Some(PWObject::Port { data }) => {
+                let linked_nodes = self
+                    .links_from_port
+                    .get(&id)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|link| match self.get(link) {
+                        Some(PWObject::Link { data }) => data
+                            .input_port
+                            .and_then(|input_port| self.get_port_node(&input_port)),
+                        _ => None,
+                    });
+                data.node_id.into_iter().chain(linked_nodes).collect()
+            }
+            Some(PWObject::Link { data }) => {
+                let input_node = data
+                    .input_port
+                    .and_then(|input_port| self.get_port_node(&input_port));
+                let output_node = data
+                    .output_port
+                    .and_then(|output_port| self.get_port_node(&output_port))
+                    .filter(|output_node| self.sinks.contains(output_node));
+                input_node.into_iter().chain(output_node).collect()
+            }
+            Some(PWObject::Client { .. } | PWObject::Device { .. }) => self
+                .owned_nodes
+                .get(&id)
+                .into_iter()
+                .flatten()
+                .copied()
+                .collect(),
+            Some(PWObject::Metadata) | None => Vec::new(),
+        }
+    }
+
+    /// Gets the id of the node a port belongs to
+    fn get_port_node(&self, port: &Id) -> Option<Id> {
+        match self.get(port) {
+            Some(PWObject::Port { data }) => data.node_id,
+            _ => None,
+        }
+    }
+
+    /// Drops the cached activity of every sink whose walk reached any of the given nodes, so that
+    /// it is recomputed by [Self::get_active_nodes].
+    fn invalidate_nodes(&mut self, nodes: &[Id]) {
+        if nodes.is_empty() {
+            return;
+        }
+
+        let outdated_sinks: Vec<Id> = self
+            .sink_activity
+            .iter()
+            .filter(|(_, activity)| {
+                nodes
+                    .iter()
+                    .any(|node| activity.reached_nodes.contains(node))
+            })
+            .map(|(sink, _)| *sink)
+            .collect();
+        for sink in outdated_sinks {
+            trace!(target: "PWGraph::invalidate_nodes", "Activity of Sink ({sink}) is outdated");
+            self.drop_sink_activity(sink);
+        }
+    }
+
+    /// Drops the cached activity of a sink, marking the nodes it found as active to be merged
+    /// again.
+    fn drop_sink_activity(&mut self, sink: Id) {
+        if let Some(activity) = self.sink_activity.remove(&sink) {
+            self.changed_nodes.extend(activity.active_nodes.into_keys());
+        }
+    }
+
+    /// Drops the cached activity of every sink, so that they are all walked again by
+    /// [Self::get_active_nodes].
+    pub fn clear_sink_activity(&mut self) {
+        let sinks: Vec<Id> = self.sink_activity.keys().copied().collect();
+        for sink in sinks {
+            self.drop_sink_activity(sink);
+        }
+    }
+
+    /// Checks if the mute and volume of nodes are used, and thus must be tracked
+    pub fn watches_volume(&self) -> bool {
+        self.config.ignore_muted || self.config.minimum_volume.is_some()
+    }
+
+    /// Gets the data of the client that owns a node, if it is tracked
+    fn get_client(&self, node: &NodeData) -> Option<&ClientData> {
+        match self.get(&node.client_id?) {
+            Some(PWObject::Client { data, .. }) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Gets the data of the device a node belongs to, if it is tracked
+    fn get_device(&self, node: &NodeData) -> Option<&DeviceData> {
+        match self.get(&node.device_id?) {
+            Some(PWObject::Device { data, .. }) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Builds the [NodeContext] of a node, used to match it against [NodeFilter]s and
+    /// [SinkFilter]s
+    fn get_node_context<'a>(&'a self, node: &'a NodeData) -> NodeContext<'a> {
+        NodeContext {
+            node,
+            client: self.get_client(node),
+            device: self.get_device(node),
+            is_default_sink: self.is_default_sink(node),
+        }
+    }
+
+    /// Checks if a node is the current default sink. The default sink chosen by the PipeWire
+    /// session manager has priority over the one configured by the user, as the later may not be
+    /// available.
+    fn is_default_sink(&self, node: &NodeData) -> bool {
+        let default_sink = self
+            .default_sink
+            .as_deref()
+            .or(self.configured_default_sink.as_deref());
+        default_sink.is_some_and(|default_sink| node.name.as_deref() == Some(default_sink))
+    }
+
+    /// Updates the name of the default sink, as announced by the "default" metadata object, and
+    /// recomputes the watched sinks.
     ///
-    /// If a sink_whitelist is passed to the graph, only sinks that match it will be treated.
-    pub fn is_any_sink_active(&self) -> bool {
-        if self.sinks.is_empty() {
-            warn!(target: "PWGraph::is_any_sink_active", "List of sinks is empty");
+    /// Returns true if the default sink was changed.
+    pub fn set_default_sink(&mut self, key: DefaultSinkKey, sink_name: Option<String>) -> bool {
+        let previous = (
+            self.default_sink.clone(),
+            self.configured_default_sink.clone(),
+        );
+
+        match key {
+            DefaultSinkKey::Default => self.default_sink = sink_name,
+            DefaultSinkKey::Configured => self.configured_default_sink = sink_name,
+            DefaultSinkKey::All => {
+                self.default_sink = None;
+                self.configured_default_sink = None;
+            }
+        }
+
+        if previous
+            == (
+                self.default_sink.clone(),
+                self.configured_default_sink.clone(),
+            )
+        {
+            return false;
         }
 
-        for sink in &self.sinks {
-            trace!(target: "PWgraph::is_any_sink_active", "Starting transversal from Sink {sink}");
-            if self.does_sink_has_active_nodes(sink, &mut HashSet::new()) {
-                return true;
+        debug!(
+            target: "PWGraph::set_default_sink",
+            "Default sink changed to {:?}, configured default sink changed to {:?}",
+            self.default_sink,
+            self.configured_default_sink
+        );
+        self.update_all_sinks();
+        self.clear_sink_activity();
+        true
+    }
+
+    /// Checks if a node is a sink that should be watched. If a sink_whitelist was passed, the sink
+    /// must match it.
+    fn is_watched_sink(&self, node: &NodeData) -> bool {
+        node.media_class
+            .as_deref()
+            .is_some_and(|media_class| media_class.contains("Sink"))
+            && (self.config.sink_whitelist.is_empty()
+                || SinkFilter::matches_any(
+                    &self.config.sink_whitelist,
+                    &self.get_node_context(node),
+                ))
+    }
+
+    /// Checks again if the given nodes are watched sinks, adding or removing them from the set of
+    /// watched sinks. Nodes that are no longer in the graph are removed.
+    ///
+    /// As sink filters may depend on the node's device, this is done for the node that was
+    /// inserted, updated or removed, or for the nodes of the Device that was.
+    fn update_sinks(&mut self, nodes: &[Id]) {
+        for id in nodes {
+            let is_watched_sink = match self.get(id) {
+                Some(PWObject::Node { data, .. }) => self.is_watched_sink(data),
+                _ => false,
+            };
+
+            if is_watched_sink {
+                if self.sinks.insert(*id) {
+                    debug!(target: "PWGraph::update_sinks", "Node ({id}) is now a watched sink");
+                }
+            } else if self.sinks.remove(id) {
+                debug!(target: "PWGraph::update_sinks", "Node ({id}) is no longer a watched sink");
+                self.drop_sink_activity(*id);
             }
         }
+    }
 
-        false
+    /// Recomputes the whole set of watched sinks. This is only done when the default sink changes,
+    /// as any node may start or stop matching the sink filters.
+    fn update_all_sinks(&mut self) {
+        let sinks: HashSet<Id> = self
+            .objects
+            .iter()
+            .filter_map(|(id, obj)| match obj {
+                PWObject::Node { data, .. } if self.is_watched_sink(data) => Some(*id),
+                _ => None,
+            })
+            .collect();
+
+        if sinks != self.sinks {
+            debug!(target: "PWGraph::update_all_sinks", "Watched sinks changed to {sinks:?}");
+            let unwatched_sinks: Vec<Id> = self
+                .sink_activity
+                .keys()
+                .filter(|sink| !sinks.contains(sink))
+                .copied()
+                .collect();
+            self.sinks = sinks;
+            for sink in unwatched_sinks {
+                self.drop_sink_activity(sink);
+            }
+        }
     }
 
-    /// Transverses the Graphs in a manner similar to a DFS algorithm, looking for active
-    /// connections from sinks to nodes.
+    /// Looks for sinks with active links to tracked nodes and collects the client nodes that are
+    /// currently playing into them.
     ///
-    /// If a node_blacklist was passed, nodes that match it will be ignored.
-    fn does_sink_has_active_nodes(&self, id: &Id, visited: &mut HashSet<Id>) -> bool {
-        visited.insert(*id);
+    /// If a sink_whitelist is passed to the graph, only sinks that match it will be treated. If
+    /// configured, nodes recording the sinks are also collected.
+    ///
+    /// Only sinks whose cached activity is outdated are walked again, and only the entries of the
+    /// nodes they found are updated in the returned map.
+    pub fn get_active_nodes(&mut self) -> &HashMap<Id, ActiveNode> {
+        if self.sinks.is_empty() {
+            warn!(target: "PWGraph::get_active_nodes", "List of sinks is empty");
+        }
 
-        trace!(target: "PWGraph::does_sink_has_active_nodes", "Node {id}");
-        match self.get(id) {
-            Some(PWObject::Node { data, .. }) => {
-                if NodeFilter::matches_any(&self.node_blacklist, data) {
-                    return false;
+        let outdated_sinks: Vec<Id> = self
+            .sinks
+            .iter()
+            .filter(|sink| !self.sink_activity.contains_key(sink))
+            .copied()
+            .collect();
+        for sink in outdated_sinks {
+            let sink_activity = self.find_sink_activity(&sink);
+            self.changed_nodes
+                .extend(sink_activity.active_nodes.keys().copied());
+            self.sink_activity.insert(sink, sink_activity);
+        }
+
+        for id in self.changed_nodes.drain() {
+            self.active_nodes.remove(&id);
+            for sink_activity in self.sink_activity.values() {
+                let Some(active_node) = sink_activity.active_nodes.get(&id) else {
+                    continue;
+                };
+                for path in &active_node.paths {
+                    Self::insert_active_node(&mut self.active_nodes, id, path.clone());
                 }
             }
+        }
+
+        &self.active_nodes
+    }
+
+    /// Gets the nodes whose info events may change the decision, that is, the watched sinks and
+    /// every node reached by their walks. The rest of the nodes are only known by their registry
+    /// properties.
+    ///
+    /// Only the walks cached by the last call to [Self::get_active_nodes] are considered.
+    pub fn get_watched_nodes(&self) -> HashSet<Id> {
+        self.sinks
+            .iter()
+            .chain(
+                self.sink_activity
+                    .values()
+                    .flat_map(|sink_activity| &sink_activity.reached_nodes),
+            )
+            .copied()
+            .collect()
+    }
+
+    /// Walks the graph from a single sink, collecting the client nodes playing into it and, if
+    /// configured, the nodes recording it.
+    fn find_sink_activity(&self, sink: &Id) -> SinkActivity {
+        let mut sink_activity = SinkActivity::default();
+        sink_activity.reached_nodes.insert(*sink);
+
+        let Some(PWObject::Node { data }) = self.get(sink) else {
+            warn!(target: "PWGraph::find_sink_activity", "Expected Sink Node, but got something else with id {sink}");
+            return sink_activity;
+        };
+        trace!(target: "PWgraph::find_sink_activity", "Starting transversal from Sink {sink}");
+        self.find_active_nodes(
+            sink,
+            self.get_node_context(data),
+            false,
+            &mut vec![*sink],
+            &mut HashMap::new(),
+            &mut sink_activity,
+        );
+
+        if self.config.detect_recording {
+            self.find_recorders(sink, &mut sink_activity);
+        }
+
+        sink_activity
+    }
+
+    /// Transverses the Graphs in a manner similar to a DFS algorithm, looking for active
+    /// connections from sinks to nodes. Every client node reached through active links is inserted
+    /// into the [SinkActivity], while other nodes are walked across.
+    ///
+    /// If a node_blacklist was passed, nodes that match it, and every node only reached through
+    /// them, are marked as blacklisted. The same happens to nodes that, together with the sink at
+    /// the root of the walk, match the route_blacklist. The visited map stores if a node was only
+    /// reached through blacklisted nodes, so that it is visited again if a path without them is
+    /// found.
+    ///
+    /// If configured, paths through muted nodes or nodes below the minimum volume are ignored, as
+    /// well as passive links and links to or from passive nodes.
+    ///
+    /// The path stack holds the nodes walked from the sink to the current node, and is stored in
+    /// the [ActiveNode] of every client found. As nodes are not visited twice, only the first path
+    /// found to each client is stored, unless a less blacklisted one is found later.
+    fn find_active_nodes(
+        &self,
+        id: &Id,
+        sink: NodeContext,
+        is_blacklisted: bool,
+        path: &mut Vec<Id>,
+        visited: &mut HashMap<Id, bool>,
+        sink_activity: &mut SinkActivity,
+    ) {
+        visited.insert(*id, is_blacklisted);
+        sink_activity.reached_nodes.insert(*id);
+
+        trace!(target: "PWGraph::find_active_nodes", "Node {id}");
+        let data = match self.get(id) {
+            Some(PWObject::Node { data, .. }) => data,
             None => {
-                warn!(target: "PWGraph::does_sink_has_active_nodes", "While transversing graph, got invalid id {id}");
-                return false;
+                warn!(target: "PWGraph::find_active_nodes", "While transversing graph, got invalid id {id}");
+                return;
             }
             _ => {
-                warn!(target: "PWGraph::does_sink_has_active_nodes", "While transversing graph expected Node, but got something else with id {id}");
-                return false;
+                warn!(target: "PWGraph::find_active_nodes", "While transversing graph expected Node, but got something else with id {id}");
+                return;
             }
         };
 
-        let Some(node_input_ports) = self.node_input_ports.get(id) else {
-            trace!(target: "PWGraph::does_sink_has_active_nodes", "Node ({id}) has no input ports, assuming it is a client");
-            return true;
-        };
+        if self.is_silenced(data) {
+            trace!(target: "PWGraph::find_active_nodes", "Node ({id}) is muted or below the minimum volume");
+            return;
+        }
+
+        let node = self.get_node_context(data);
+        let is_blacklisted = is_blacklisted
+            || NodeFilter::matches_any(&self.config.node_blacklist, &node)
+            || RouteFilter::matches_any(&self.config.route_blacklist, &Route { node, sink });
+
+        let node_input_ports = self
+            .node_input_ports
+            .get(id)
+            .filter(|node_input_ports| !node_input_ports.is_empty());
+
+        if self.is_client(data, node_input_ports.is_some()) {
+            trace!(target: "PWGraph::find_active_nodes", "Node ({id}) is a client");
+            let path = self.get_active_path(path, is_blacklisted);
+            Self::insert_active_node(&mut sink_activity.active_nodes, *id, path);
+            return;
+        }
 
-        if node_input_ports.is_empty() {
-            trace!(target: "PWGraph::does_sink_has_active_nodes", "Node ({id}) has no input ports, assuming it is a client");
-            return true;
+        let Some(node_input_ports) = node_input_ports else {
+            trace!(target: "PWGraph::find_active_nodes", "Node ({id}) has no input ports and is not a client");
+            return;
         };
 
+        if self.config.ignore_passive && data.is_input_passive() {
+            trace!(target: "PWGraph::find_active_nodes", "Node ({id}) has passive input ports");
+            return;
+        }
+
         trace!(
-            target: "PWGraph::does_sink_has_active_nodes",
+            target: "PWGraph::find_active_nodes",
             "Transversing Graph: Node {id}: Node Input Ports: {}",
             node_input_ports.len()
         );
@@ -427,31 +867,32 @@ impl PWGraph {
         let mut links_to_node: HashSet<(&Id, &Id)> = HashSet::new();
         for port in node_input_ports {
             let Some(PWObject::Port { .. }) = self.get(port) else {
-                warn!(target: "PWGraph::does_sink_has_active_nodes", "While transversing graph, expected Port, got something else with id {port}");
+                warn!(target: "PWGraph::find_active_nodes", "While transversing graph, expected Port, got something else with id {port}");
                 continue;
             };
             trace!("Transversing Graph: Node {id}: Input Port {port}");
             let Some(links) = self.links_to_port.get(port) else {
-                trace!(target: "PWGraph::does_sink_has_active_nodes", "Transversing Graph: Node {id}: No links to Input Port {port}");
+                trace!(target: "PWGraph::find_active_nodes", "Transversing Graph: Node {id}: No links to Input Port {port}");
                 continue;
             };
             if links.is_empty() {
-                trace!(target: "PWGraph::does_sink_has_active_nodes", "Transversing Graph: Node {id}: No links to Input Port {port}");
+                trace!(target: "PWGraph::find_active_nodes", "Transversing Graph: Node {id}: No links to Input Port {port}");
                 continue;
             };
             trace!(
-                target: "PWGraph::does_sink_has_active_nodes",
+                target: "PWGraph::find_active_nodes",
                 "Transversing Graph: Node {id}: links to Input Port {port}: {}",
                 links.len()
             );
             for link in links {
                 let Some(PWObject::Link { data, .. }) = self.get(link) else {
-                    warn!(target: "PWGraph::does_sink_has_active_nodes", "While transversing graph, expected Link, got something else with id {link}");
+                    warn!(target: "PWGraph::find_active_nodes", "While transversing graph, expected Link, got something else with id {link}");
                     continue;
                 };
                 let LinkData {
                     output_port,
                     active,
+                    passive,
                     ..
                 } = data;
 
@@ -463,8 +904,13 @@ impl PWGraph {
                     continue;
                 }
 
+                if self.config.ignore_passive && *passive == Some(true) {
+                    trace!(target: "PWGraph::find_active_nodes", "Transversing Graph: Node {id}: Link ({link}) is passive");
+                    continue;
+                }
+
                 let Some(output_port) = output_port else {
-                    warn!(target: "PWGraph::does_sink_has_active_nodes", "Link ({link}) is missing output_port");
+                    warn!(target: "PWGraph::find_active_nodes", "Link ({link}) is missing output_port");
                     continue;
                 };
 
@@ -473,28 +919,203 @@ impl PWGraph {
         }
 
         if links_to_node.is_empty() {
-            trace!(target: "PWGraph::does_sink_has_active_nodes", "Transversing Graph: Node {id}: No Active Links to node");
-            return false;
+            trace!(target: "PWGraph::find_active_nodes", "Transversing Graph: Node {id}: No Active Links to node");
+            return;
         };
-        trace!(target: "PWGraph::does_sink_has_active_nodes", "Transversing Graph: Node {id}: Active Links to node: {}", links_to_node.len());
+        trace!(target: "PWGraph::find_active_nodes", "Transversing Graph: Node {id}: Active Links to node: {}", links_to_node.len());
 
         for (_, input_port) in links_to_node {
             let Some(PWObject::Port { data, .. }) = self.get(input_port) else {
-                warn!(target: "PWGraph::does_sink_has_active_nodes", "While transversing graph, expected Port, got something else with id {input_port}");
+                warn!(target: "PWGraph::find_active_nodes", "While transversing graph, expected Port, got something else with id {input_port}");
                 continue;
             };
             let PortData { node_id, .. } = data;
 
             let Some(node_id) = node_id else {
-                warn!(target: "PWGraph::does_sink_has_active_nodes", "Port ({input_port}) is missing node_id");
+                warn!(target: "PWGraph::find_active_nodes", "Port ({input_port}) is missing node_id");
                 continue;
             };
 
-            if !visited.contains(node_id) && self.does_sink_has_active_nodes(node_id, visited) {
-                return true;
+            if self.config.ignore_passive && self.is_output_passive(node_id) {
+                sink_activity.reached_nodes.insert(*node_id);
+                trace!(target: "PWGraph::find_active_nodes", "Transversing Graph: Node {node_id} has passive output ports");
+                continue;
+            }
+
+            let should_visit = visited
+                .get(node_id)
+                .is_none_or(|was_blacklisted| *was_blacklisted && !is_blacklisted);
+            if should_visit {
+                path.push(*node_id);
+                self.find_active_nodes(node_id, sink, is_blacklisted, path, visited, sink_activity);
+                path.pop();
             }
         }
+    }
+
+    /// Looks for nodes recording a sink, that is, `Stream/Input/*` nodes with active links from the
+    /// sink's monitor ports. Recorders are inserted into the [SinkActivity], as clients are.
+    ///
+    /// If a recorder_whitelist was passed, only recorders that match it are considered. Links from
+    /// monitor ports are usually passive, so passive links are not ignored here.
+    fn find_recorders(&self, id: &Id, sink_activity: &mut SinkActivity) {
+        let Some(node_output_ports) = self.node_output_ports.get(id) else {
+            trace!(target: "PWGraph::find_recorders", "Sink ({id}) has no output ports");
+            return;
+        };
+
+        for port in node_output_ports {
+            let Some(PWObject::Port { data, .. }) = self.get(port) else {
+                warn!(target: "PWGraph::find_recorders", "Expected Port, got something else with id {port}");
+                continue;
+            };
+            if data.is_monitor != Some(true) {
+                continue;
+            }
+
+            let Some(links) = self.links_from_port.get(port) else {
+                continue;
+            };
+            for link in links {
+                let Some(PWObject::Link { data, .. }) = self.get(link) else {
+                    warn!(target: "PWGraph::find_recorders", "Expected Link, got something else with id {link}");
+                    continue;
+                };
+                if data.active != Some(true) {
+                    continue;
+                }
 
-        false
+                let Some(PWObject::Port {
+                    data:
+                        PortData {
+                            node_id: Some(node_id),
+                            ..
+                        },
+                    ..
+                }) = data.input_port.and_then(|input_port| self.get(&input_port))
+                else {
+                    warn!(target: "PWGraph::find_recorders", "Link ({link}) is missing input_port");
+                    continue;
+                };
+                sink_activity.reached_nodes.insert(*node_id);
+                let Some(PWObject::Node { data, .. }) = self.get(node_id) else {
+                    warn!(target: "PWGraph::find_recorders", "Expected Node, got something else with id {node_id}");
+                    continue;
+                };
+
+                let is_recorder = data
+                    .media_class
+                    .as_deref()
+                    .is_some_and(|media_class| media_class.starts_with("Stream/Input/"));
+                if !is_recorder {
+                    continue;
+                }
+
+                let node = self.get_node_context(data);
+                if !self.config.recorder_whitelist.is_empty()
+                    && !NodeFilter::matches_any(&self.config.recorder_whitelist, &node)
+                {
+                    trace!(target: "PWGraph::find_recorders", "Node ({node_id}) does not match the recorder whitelist");
+                    continue;
+                }
+
+                trace!(target: "PWGraph::find_recorders", "Node ({node_id}) is recording Sink ({id})");
+                let is_blacklisted = NodeFilter::matches_any(&self.config.node_blacklist, &node);
+                let path = self.get_active_path(&[*id, *node_id], is_blacklisted);
+                Self::insert_active_node(&mut sink_activity.active_nodes, *node_id, path);
+            }
+        }
+    }
+
+    /// Checks if a node is a client that plays media, that is, if its media class matches any of
+    /// the client media classes. Every other node with a media class, such as virtual sinks and
+    /// filters, is only passed through, and sources are dead ends.
+    ///
+    /// Nodes without a media class are assumed to be clients if they have no input ports.
+    fn is_client(&self, node: &NodeData, has_input_ports: bool) -> bool {
+        match node.media_class {
+            Some(ref media_class) => self
+                .config
+                .client_media_classes
+                .iter()
+                .any(|client_media_class| client_media_class.is_match(media_class)),
+            None => !has_input_ports,
+        }
+    }
+
+    /// Checks if the output ports of a node are passive
+    fn is_output_passive(&self, id: &Id) -> bool {
+        match self.get(id) {
+            Some(PWObject::Node { data, .. }) => data.is_output_passive(),
+            _ => false,
+        }
+    }
+
+    /// Checks if a node is muted or has a volume below the minimum, if the graph is configured to
+    /// ignore such nodes. The volume is compared on a cubic scale, the same used by volume
+    /// controls such as pavucontrol.
+    fn is_silenced(&self, node: &NodeData) -> bool {
+        let is_muted = self.config.ignore_muted && node.mute == Some(true);
+        let is_below_minimum_volume = self.config.minimum_volume.is_some_and(|minimum_volume| {
+            node.volume
+                .is_some_and(|volume| volume.cbrt() * 100.0 < minimum_volume)
+        });
+
+        is_muted || is_below_minimum_volume
+    }
+
+    /// Builds an [ActivePath] from a stack of node ids walked from the sink, with the client node at
+    /// the top. The client is named after its application, if known, and every other node by its
+    /// "pretty" name, falling back to its id.
+    fn get_active_path(&self, path: &[Id], is_blacklisted: bool) -> ActivePath {
+        let nodes = path
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, id)| {
+                let Some(PWObject::Node { data, .. }) = self.get(id) else {
+                    return id.to_string();
+                };
+                let app_name = if i == 0 {
+                    data.app_name.as_deref().or(self
+                        .get_client(data)
+                        .and_then(|client| client.app_name.as_deref()))
+                } else {
+                    None
+                };
+                app_name
+                    .or(data.get_name())
+                    .map_or_else(|| id.to_string(), String::from)
+            })
+            .collect();
+
+        ActivePath {
+            nodes,
+            is_blacklisted,
+        }
+    }
+
+    /// Inserts a client node into the map of active nodes, together with the path through which it
+    /// was found. A node is only considered blacklisted if all paths to it are blacklisted.
+    fn insert_active_node(active_nodes: &mut HashMap<Id, ActiveNode>, id: Id, path: ActivePath) {
+        let active_node = active_nodes.entry(id).or_insert(ActiveNode {
+            is_blacklisted: true,
+            paths: Vec::new(),
+        });
+        active_node.is_blacklisted &= path.is_blacklisted;
+        if !active_node.paths.contains(&path) {
+            active_node.paths.push(path);
+        }
+    }
+}
+
+/// Removes an id from the set of an index, such as the links to a port, dropping the set once it is
+/// empty, so that no stale entries are left for reused ids
+fn remove_from_index(index: &mut HashMap<Id, HashSet<Id>>, key: &Id, id: Id) {
+    if let Some(ids) = index.get_mut(key) {
+        ids.remove(&id);
+        if ids.is_empty() {
+            index.remove(key);
+        }
     }
 }
@@ -1,4 +1,4 @@
-// Copyright (C) 2024  Rafael Carvalho <contact@rafaelrc.com>
+// Copyright (C) 2024-2026  Rafael Carvalho <contact@rafaelrc.com>
 
 // This program is free software: you can redistribute it and/or modify
 // it under the terms of the GNU General Public License version 3 as published by
@@ -15,30 +15,28 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 //! Object types used to represent [super::PWGraph] elements.
+//!
+//! They only hold plain data, without any [pipewire] type, so that graphs may be built and tested
+//! without a connection to the PipeWire server. They may also be serialised, so that the changes
+//! made to a graph can be recorded and replayed.
 
-use pipewire::{
-    link::{Link, LinkListener},
-    node::{Node, NodeListener},
-    port::{Port, PortListener},
-    proxy::{Listener, ProxyT},
-    spa::utils::Direction,
-};
+use serde::{Deserialize, Serialize};
 
 /// Type used by the [pipewire] crate API to represent object ids.
 pub type Id = u32;
 
-/// Generic struct that joins a [pipewire] [ProxyT], a reference to a global object, and its
-/// respective [Listener].
-pub struct Proxy<TProxy: ProxyT, TListener: Listener> {
-    pub _proxy: TProxy,
-    pub _listener: TListener,
-}
+/// Type of the `object.serial` property. Unlike [Id]s, that are quickly reused by the PipeWire
+/// server, serials are unique, and thus tell apart objects that had the same id at different times.
+pub type Serial = u64;
 
 /// Struct representing relevant data of a [pipewire::node::Node] used by the app.
 ///
 /// When the global object is first registered, it comes without data, and its fields may be
 /// optionally filled by update events. Thus, all fields are [Option]s.
-#[derive(PartialEq, Debug, Clone)]
+///
+/// The mute and volume fields are not node properties, but are read from the node's `Props` param,
+/// and are only tracked if needed.
+#[derive(PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
 pub struct NodeData {
     pub name: Option<String>,
     pub app_name: Option<String>,
@@ -47,6 +45,13 @@ pub struct NodeData {
     pub media_class: Option<String>,
     pub media_role: Option<String>,
     pub media_software: Option<String>,
+    pub client_id: Option<Id>,
+    pub device_id: Option<Id>,
+    /// Value of `node.passive`, that may be a boolean or the direction of the passive ports
+    pub passive: Option<String>,
+    pub mute: Option<bool>,
+    /// Highest linear volume of the node's channels
+    pub volume: Option<f32>,
 }
 
 impl NodeData {
@@ -62,6 +67,16 @@ impl NodeData {
             .or(self.name.as_deref())
     }
 
+    /// Checks if links to the node's input ports are passive, and thus don't keep it running
+    pub fn is_input_passive(&self) -> bool {
+        matches!(self.passive.as_deref(), Some("true" | "in" | "in-out"))
+    }
+
+    /// Checks if links from the node's output ports are passive, and thus don't keep it running
+    pub fn is_output_passive(&self) -> bool {
+        matches!(self.passive.as_deref(), Some("true" | "out" | "in-out"))
+    }
+
     /// Updates fields if new data is give.
     ///
     /// Returns true if any field was updated, false otherwise.
@@ -106,23 +121,69 @@ impl NodeData {
             was_updated = true;
         }
 
+        if new.client_id.is_some() && self.client_id != new.client_id {
+            self.client_id = new.client_id;
+            was_updated = true;
+        }
+
+        if new.device_id.is_some() && self.device_id != new.device_id {
+            self.device_id = new.device_id;
+            was_updated = true;
+        }
+
+        if new.passive.is_some() && self.passive != new.passive {
+            self.passive = new.passive;
+            was_updated = true;
+        }
+
+        if new.mute.is_some() && self.mute != new.mute {
+            self.mute = new.mute;
+            was_updated = true;
+        }
+
+        if new.volume.is_some() && self.volume != new.volume {
+            self.volume = new.volume;
+            was_updated = true;
+        }
+
         was_updated
     }
 }
 
+/// Direction of a port, from the `port.direction` property
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Input,
+    Output,
+}
+
 /// Struct representing relevant data of a [pipewire::port::Port] used by the app.
 ///
-/// When the global object is first registered, it comes without data, and its fields may be
-/// optionally filled by update events. Thus, all fields are [Option]s.
-#[derive(PartialEq, Debug, Clone)]
+/// Ports are not bound, and are only known by their registry properties, that do not change. Some
+/// of them may still be missing, and thus all fields are [Option]s.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct PortData {
     pub name: Option<String>,
     pub node_id: Option<Id>,
     pub direction: Option<Direction>,
     pub is_terminal: Option<bool>,
+    pub is_monitor: Option<bool>,
 }
 
-impl PortData {
+/// Struct representing relevant data of a [pipewire::link::Link] used by the app.
+///
+/// When the global object is first registered, it comes without data, and its fields may be
+/// optionally filled by update events. Thus, all fields are [Option]s.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct LinkData {
+    pub input_port: Option<Id>,
+    pub output_port: Option<Id>,
+    pub active: Option<bool>,
+    pub passive: Option<bool>,
+}
+
+impl LinkData {
     /// Updates fields if new data is give.
     ///
     /// Returns true if any field was updated, false otherwise.
@@ -132,23 +193,23 @@ impl PortData {
     pub fn update(&mut self, new: Self) -> bool {
         let mut was_updated = false;
 
-        if new.name.is_some() && self.name != new.name {
-            self.name = new.name;
+        if new.input_port.is_some() && self.input_port != new.input_port {
+            self.input_port = new.input_port;
             was_updated = true;
         }
 
-        if new.node_id.is_some() && self.node_id != new.node_id {
-            self.node_id = new.node_id;
+        if new.output_port.is_some() && self.output_port != new.output_port {
+            self.output_port = new.output_port;
             was_updated = true;
         }
 
-        if new.direction.is_some() && self.direction != new.direction {
-            self.direction = new.direction;
+        if new.active.is_some() && self.active != new.active {
+            self.active = new.active;
             was_updated = true;
         }
 
-        if new.is_terminal.is_some() && self.is_terminal != new.is_terminal {
-            self.is_terminal = new.is_terminal;
+        if new.passive.is_some() && self.passive != new.passive {
+            self.passive = new.passive;
             was_updated = true;
         }
 
@@ -156,18 +217,19 @@ impl PortData {
     }
 }
 
-/// Struct representing relevant data of a [pipewire::link::Link] used by the app.
+/// Struct representing relevant data of a [pipewire::client::Client] used by the app. Clients are
+/// the applications connected to the PipeWire server, that own the nodes they create.
 ///
 /// When the global object is first registered, it comes without data, and its fields may be
 /// optionally filled by update events. Thus, all fields are [Option]s.
-#[derive(PartialEq, Debug, Clone)]
-pub struct LinkData {
-    pub input_port: Option<Id>,
-    pub output_port: Option<Id>,
-    pub active: Option<bool>,
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ClientData {
+    pub app_name: Option<String>,
+    pub process_binary: Option<String>,
+    pub app_id: Option<String>,
 }
 
-impl LinkData {
+impl ClientData {
     /// Updates fields if new data is give.
     ///
     /// Returns true if any field was updated, false otherwise.
@@ -177,18 +239,71 @@ impl LinkData {
     pub fn update(&mut self, new: Self) -> bool {
         let mut was_updated = false;
 
-        if new.input_port.is_some() && self.input_port != new.input_port {
-            self.input_port = new.input_port;
+        if new.app_name.is_some() && self.app_name != new.app_name {
+            self.app_name = new.app_name;
             was_updated = true;
         }
 
-        if new.output_port.is_some() && self.output_port != new.output_port {
-            self.output_port = new.output_port;
+        if new.process_binary.is_some() && self.process_binary != new.process_binary {
+            self.process_binary = new.process_binary;
             was_updated = true;
         }
 
-        if new.active.is_some() && self.active != new.active {
-            self.active = new.active;
+        if new.app_id.is_some() && self.app_id != new.app_id {
+            self.app_id = new.app_id;
+            was_updated = true;
+        }
+
+        was_updated
+    }
+}
+
+/// Struct representing relevant data of a [pipewire::device::Device] used by the app. Devices are
+/// the hardware (or virtual) devices that own sink nodes.
+///
+/// When the global object is first registered, it comes without data, and its fields may be
+/// optionally filled by update events. Thus, all fields are [Option]s.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceData {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub api: Option<String>,
+    pub bus: Option<String>,
+    pub form_factor: Option<String>,
+}
+
+impl DeviceData {
+    /// Updates fields if new data is give.
+    ///
+    /// Returns true if any field was updated, false otherwise.
+    ///
+    /// [pipewire] update events don't provide already existing data, only new one. Thus, only
+    /// [Some] values should be used, as it represents data that should replace the current one.
+    pub fn update(&mut self, new: Self) -> bool {
+        let mut was_updated = false;
+
+        if new.name.is_some() && self.name != new.name {
+            self.name = new.name;
+            was_updated = true;
+        }
+
+        if new.description.is_some() && self.description != new.description {
+            self.description = new.description;
+            was_updated = true;
+        }
+
+        if new.api.is_some() && self.api != new.api {
+            self.api = new.api;
+            was_updated = true;
+        }
+
+        if new.bus.is_some() && self.bus != new.bus {
+            self.bus = new.bus;
+            was_updated = true;
+        }
+
+        if new.form_factor.is_some() && self.form_factor != new.form_factor {
+            self.form_factor = new.form_factor;
             was_updated = true;
         }
 
@@ -197,26 +312,38 @@ impl LinkData {
 }
 
 /// Enum of all [PWObject] data variants. Used by polymorphic functions over only the object data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PWObjectData {
     Node(NodeData),
-    Port(PortData),
     Link(LinkData),
+    Client(ClientData),
+    Device(DeviceData),
 }
 
 /// Enum of all tracked types of [pipewire] graph elements.
 ///
-/// The variants are structs of the object data and its [Proxy].
+/// The variants are structs of the object data. The proxies of the objects are not stored here, so
+/// that graphs can be built without a connection to the PipeWire server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PWObject {
     Node {
         data: NodeData,
-        _proxy: Proxy<Node, NodeListener>,
     },
     Port {
         data: PortData,
-        _proxy: Proxy<Port, PortListener>,
     },
     Link {
         data: LinkData,
-        _proxy: Proxy<Link, LinkListener>,
     },
+    Client {
+        data: ClientData,
+    },
+    Device {
+        data: DeviceData,
+    },
+    /// The "default" metadata object, that holds the default sink. It has no data of its own, as
+    /// it is stored directly in the [super::PWGraph].
+    Metadata,
 }
@@ -18,51 +18,197 @@
 //! nodes, ports and connections.
 //!
 //! The graph is used to detect if any connection to watched sinks is active.
+//!
+//! Each PipeWire remote, e.g. the default one and the one of a container, is watched by its own
+//! [PWThread], with its own graph and reconnection handling.
+//!
+//! The changes made to the graphs may also be recorded into a [trace], to be replayed offline.
 
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
+    collections::HashMap,
     marker::Send,
     rc::Rc,
+    sync::Arc,
     thread::{self, JoinHandle},
+    time::Duration,
 };
 
 use pipewire::{
+    client::{Client, ClientInfoRef, ClientListener},
     context::ContextRc,
+    core::{CoreRc, Listener as CoreListener, PW_ID_CORE},
+    device::{Device, DeviceInfoRef, DeviceListener},
     keys,
     link::{Link, LinkChangeMask, LinkInfoRef, LinkListener, LinkState},
     main_loop::MainLoopRc,
+    metadata::{Metadata, MetadataListener},
     node::{Node, NodeInfoRef, NodeListener},
-    port::{Port, PortInfoRef, PortListener},
-    registry::{GlobalObject, RegistryRc},
-    spa::utils::{Direction, dict::DictRef},
+    properties::{PropertiesBox, properties},
+    registry::{GlobalObject, Listener as RegistryListener, RegistryRc},
+    spa::{
+        param::ParamType,
+        pod::{Pod, Value, ValueArray, deserialize::PodDeserializer},
+        sys,
+        utils::{dict::DictRef, result::AsyncSeq},
+    },
     types::ObjectType,
 };
 
-use log::debug;
+use log::{debug, error, info, warn};
+use nix::errno::Errno;
+use serde::Deserialize;
 
 pub mod graph;
 use graph::{
-    PWGraph,
-    object::{Id, LinkData, NodeData, PWObject, PWObjectData, PortData, Proxy},
+    ActiveNode, DefaultSinkKey, GraphConfig, PWGraph,
+    object::{
+        ClientData, DeviceData, Direction, Id, LinkData, NodeData, PWObject, PWObjectData,
+        PortData, Serial,
+    },
 };
 
-use graph::filter::{NodeFilter, SinkFilter};
+mod error;
+pub use error::PWError;
+
+mod proxy;
+use proxy::{PWProxy, Proxy};
+
+pub mod trace;
+use trace::{GraphChange, TraceEvent, TraceRecorder};
 
 use crate::message_queue::MessageQueueSender;
 
+/// Delay before the first attempt to reconnect to the PipeWire server
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+
+/// Maximum delay between attempts to reconnect to the PipeWire server
+const RECONNECT_MAXIMUM_DELAY: Duration = Duration::from_secs(30);
+
+/// Client property set by the xdg-desktop-portal with the app id of sandboxed (e.g. Flatpak) apps
+const PORTAL_APP_ID: &str = "pipewire.access.portal.app_id";
+
+/// Metadata property with the name of the metadata object
+const METADATA_NAME: &str = "metadata.name";
+
+/// Name of the metadata object that holds the default nodes
+const DEFAULT_METADATA_NAME: &str = "default";
+
+/// Metadata key of the default sink currently in use
+const DEFAULT_AUDIO_SINK: &str = "default.audio.sink";
+
+/// Metadata key of the default sink configured by the user
+const DEFAULT_CONFIGURED_AUDIO_SINK: &str = "default.configured.audio.sink";
+
+/// Value of the default node metadata properties, that are JSON objects such as
+/// `{ "name": "alsa_output.pci-0000_00_1f.3.analog-stereo" }`
+#[derive(Deserialize)]
+struct DefaultNodeValue {
+    name: String,
+}
+
+/// Index of a PipeWire remote in the list of remotes watched by the caller, identifying the
+/// [PWThread] that sent an event
+pub type RemoteId = usize;
+
 /// Events that can be sent to the PipeWire thread
 #[derive(Debug)]
 pub enum PWMsg {
     Terminate,
-    GraphUpdated,
 }
 
 /// Events that are fired by the PipeWire thread and must be treated by the caller
 #[derive(Clone, Debug)]
 pub enum PWEvent {
-    GraphUpdated,
-    InhibitIdleState(bool),
-    ThreadPanic(Option<String>),
+    ActiveNodes(RemoteId, HashMap<Id, ActiveNode>),
+    /// The connection to the PipeWire remote was lost, and is being retried
+    Disconnected(RemoteId),
+    /// A fatal error, after which no more events are sent
+    Error(PWError),
+}
+
+/// Coalesces bursts of changes to the [PWGraph], such as the ones that happen at startup or when
+/// an app opens a stream.
+///
+/// Every change marks the graph as dirty and, if none is pending, requests a `core.sync` roundtrip.
+/// Once the server answers it with the `done` event, every event sent before it was already
+/// handled, and the graph is evaluated only once. The graph is also not evaluated before the
+/// first roundtrip, requested at startup, is done, that is, before the initial registry
+/// enumeration is finished.
+///
+/// Every change is made through [Self::apply], that also records it, if a trace is being recorded.
+struct GraphUpdates {
+    core: CoreRc,
+    remote_id: RemoteId,
+    recorder: Option<TraceRecorder>,
+    is_dirty: Cell<bool>,
+    is_initialized: Cell<bool>,
+    pending_sync: Cell<Option<AsyncSeq>>,
+}
+
+impl GraphUpdates {
+    fn new(core: CoreRc, remote_id: RemoteId, recorder: Option<TraceRecorder>) -> Self {
+        Self {
+            core,
+            remote_id,
+            recorder,
+            is_dirty: Cell::new(false),
+            is_initialized: Cell::new(false),
+            pending_sync: Cell::new(None),
+        }
+    }
+
+    /// Applies a change to the graph, marking it as dirty if it was changed
+    fn apply(&self, graph: &RefCell<PWGraph>, change: GraphChange) {
+        if self.recorder.is_some() {
+            self.record(TraceEvent::Change(Box::new(change.clone())));
+        }
+        if change.apply(&mut graph.borrow_mut()) {
+            self.mark_dirty();
+        }
+    }
+
+    /// Records an event of the remote, if a trace is being recorded
+    fn record(&self, event: TraceEvent) {
+        if let Some(ref recorder) = self.recorder {
+            recorder.record(self.remote_id, event);
+        }
+    }
+
+    /// Marks the graph as changed, requesting a roundtrip if none is pending
+    fn mark_dirty(&self) {
+        self.is_dirty.set(true);
+        if self.pending_sync.get().is_none() {
+            self.sync();
+        }
+    }
+
+    /// Requests a roundtrip to the PipeWire server
+    fn sync(&self) {
+        match self.core.sync(0) {
+            Ok(seq) => self.pending_sync.set(Some(seq)),
+            Err(err) => {
+                warn!(target: "GraphUpdates::sync", "Failed to sync with the PipeWire server: {err}")
+            }
+        }
+    }
+
+    /// Handles the `done` event of a roundtrip.
+    ///
+    /// Returns true if it finishes the pending roundtrip and the graph must be evaluated, that is,
+    /// if it was changed or if this is the first roundtrip.
+    fn done(&self, seq: AsyncSeq) -> bool {
+        if self.pending_sync.get() != Some(seq) {
+            return false;
+        }
+        self.pending_sync.set(None);
+
+        let was_initialized = self.is_initialized.replace(true);
+        if !was_initialized {
+            debug!(target: "GraphUpdates::done", "Initial registry enumeration finished");
+        }
+        self.is_dirty.replace(false) || !was_initialized
+    }
 }
 
 /// Wrapper around the PipeWire thread and channel
@@ -73,22 +219,38 @@ pub struct PWThread {
 
 impl PWThread {
     /// Constructor that creates the channel used by the PipeWire [MainLoop] and launches it in
-    /// another thread
+    /// another thread, connected to the named PipeWire remote, or to the default one if [None]
+    ///
+    /// If a [TraceRecorder] is given, the events of the remote are recorded with it.
     pub fn new<Msg: From<PWEvent> + Clone + Send + 'static>(
+        remote_id: RemoteId,
+        remote_name: Option<String>,
         pw_event_listener: MessageQueueSender<Msg>,
-        sink_whitelist: Vec<SinkFilter>,
-        node_blacklist: Vec<NodeFilter>,
+        graph_config: GraphConfig,
+        recorder: Option<TraceRecorder>,
     ) -> Self {
         let (pw_event_sender, pw_event_queue) = pipewire::channel::channel();
 
-        let pw_thread = thread::spawn(move || {
-            pw_thread(
-                pw_event_listener,
-                pw_event_queue,
-                sink_whitelist,
-                node_blacklist,
-            )
-        });
+        // Named after the remote, to tell which one panicked
+        let pw_thread = thread::Builder::new()
+            .name(format!("pipewire-{remote_id}"))
+            .spawn(move || {
+                if let Err(err) = pw_thread(
+                    remote_id,
+                    remote_name,
+                    pw_event_listener.clone(),
+                    pw_event_queue,
+                    graph_config,
+                    recorder,
+                ) {
+                    error!(target: "PWThread", "{err}");
+                    // Fails if the caller is no longer listening, in which case there is no one to
+                    // report the error to
+                    let _ = pw_event_listener.send(Msg::from(PWEvent::Error(err)));
+                }
+            })
+            // As thread::spawn, only fails if the OS can not create the thread
+            .expect("Failed to spawn PipeWire thread");
 
         PWThread {
             pw_thread,
@@ -113,99 +275,294 @@ impl PWThread {
     }
 }
 
-/// PipeWire thread function. Responsible to create PipeWire objects, subscribe to events and run
-/// the [MainLoop]
+/// PipeWire thread function. Responsible to create the [MainLoop], connect to the PipeWire server
+/// and run the [MainLoop].
+///
+/// If the connection fails or is lost, e.g. when the PipeWire server restarts, the caller is
+/// notified and the connection is retried with an exponential backoff. Thus, the daemon may also
+/// be started before the PipeWire server.
+///
+/// Only returns an error if it is fatal, that is, if the [MainLoop] can not run or if the caller is
+/// no longer listening to events.
 fn pw_thread<Msg: From<PWEvent> + Clone + 'static>(
+    remote_id: RemoteId,
+    remote_name: Option<String>,
     pw_event_listener: MessageQueueSender<Msg>,
     pw_event_queue: pipewire::channel::Receiver<PWMsg>,
-    sink_whitelist: Vec<SinkFilter>,
-    node_blacklist: Vec<NodeFilter>,
-) {
+    graph_config: GraphConfig,
+    recorder: Option<TraceRecorder>,
+) -> Result<(), PWError> {
     pipewire::init();
 
-    let mainloop = MainLoopRc::new(None).expect("Failed to create mainloop.");
-    let context = ContextRc::new(&mainloop, None).expect("Failed to create context.");
-    let core = context.connect_rc(None).expect("Failed to get core.");
-    let registry = core.get_registry_rc().expect("Failed to get registry");
+    let mainloop = MainLoopRc::new(None).map_err(|err| PWError::MainLoop(Arc::new(err)))?;
+    let context = ContextRc::new(&mainloop, None).map_err(|err| PWError::Context(Arc::new(err)))?;
+
+    let is_terminated = Rc::new(Cell::new(false));
+    let _receiver = pw_event_queue.attach(mainloop.loop_(), {
+        let mainloop = mainloop.clone();
+        let is_terminated = Rc::clone(&is_terminated);
+
+        // Treats events sent to the MainLoop thread by the caller
+        move |signal: PWMsg| match signal {
+            PWMsg::Terminate => {
+                is_terminated.set(true);
+                mainloop.quit();
+            }
+        }
+    });
 
-    let graph = Rc::new(RefCell::new(PWGraph::new(sink_whitelist, node_blacklist)));
+    // Stops the MainLoop once the delay before reconnecting is over
+    let reconnect_timer = mainloop.loop_().add_timer({
+        let mainloop = mainloop.clone();
+        move |_| mainloop.quit()
+    });
+
+    let remote = remote_name.as_deref().unwrap_or("default");
+    let mut reconnect_delay = RECONNECT_INITIAL_DELAY;
+    loop {
+        match connect(
+            &context,
+            &mainloop,
+            remote_id,
+            remote_name.as_deref(),
+            pw_event_listener.clone(),
+            graph_config.clone(),
+            recorder.clone(),
+        ) {
+            Ok(connection) => {
+                info!(target: "pw_thread", "Connected to the {remote} PipeWire remote");
+                reconnect_delay = RECONNECT_INITIAL_DELAY;
+
+                mainloop.run();
+                drop(connection);
+                if is_terminated.get() {
+                    break;
+                }
+
+                warn!(target: "pw_thread", "Disconnected from the {remote} PipeWire remote");
+                if let Some(ref recorder) = recorder {
+                    recorder.record(remote_id, TraceEvent::Disconnected);
+                }
+                pw_event_listener
+                    .send(Msg::from(PWEvent::Disconnected(remote_id)))
+                    .map_err(|_| PWError::ChannelClosed)?;
+            }
+            Err(err) => {
+                warn!(target: "pw_thread", "Failed to connect to the {remote} PipeWire remote: {err}");
+            }
+        }
+
+        debug!(target: "pw_thread", "Reconnecting to the {remote} PipeWire remote in {reconnect_delay:?}");
+        reconnect_timer.update_timer(Some(reconnect_delay), None);
+        mainloop.run();
+        if is_terminated.get() {
+            break;
+        }
+        reconnect_delay = (reconnect_delay * 2).min(RECONNECT_MAXIMUM_DELAY);
+    }
+
+    Ok(())
+}
+
+/// Objects that live as long as the connection to the PipeWire server, including, through their
+/// callbacks, the [PWGraph]. Dropping it disconnects from the server.
+struct PWConnection {
+    _core_listener: CoreListener,
+    _registry_listener: RegistryListener,
+    _proxies: Rc<RefCell<HashMap<Id, PWProxy>>>,
+}
+
+/// Connects to the named PipeWire remote, or to the default one, subscribing to registry events to
+/// build a new [PWGraph].
+///
+/// If the connection is lost, the [MainLoop] is stopped.
+fn connect<Msg: From<PWEvent> + Clone + 'static>(
+    context: &ContextRc,
+    mainloop: &MainLoopRc,
+    remote_id: RemoteId,
+    remote_name: Option<&str>,
+    pw_event_listener: MessageQueueSender<Msg>,
+    graph_config: GraphConfig,
+    recorder: Option<TraceRecorder>,
+) -> Result<PWConnection, pipewire::Error> {
+    let properties = remote_name.map(|remote_name| {
+        properties! {
+            *keys::REMOTE_NAME => remote_name,
+        }
+    });
+    let core = context.connect_rc(properties)?;
+    let registry = core.get_registry_rc()?;
+    let graph = Rc::new(RefCell::new(PWGraph::new(graph_config)));
+    let graph_updates = Rc::new(GraphUpdates::new(core.clone(), remote_id, recorder));
+    let proxies: Rc<RefCell<HashMap<Id, PWProxy>>> = Rc::new(RefCell::new(HashMap::new()));
+    let unbound_nodes: Rc<RefCell<HashMap<Id, UnboundNode>>> =
+        Rc::new(RefCell::new(HashMap::new()));
 
     // Listen to registry global events, that happen when objects when globals are created or
     // removed.
-    let _listener = {
+    let registry_listener = {
         registry
             .add_listener_local()
             .global({
                 // Object created
                 let registry = registry.clone();
                 let graph = Rc::clone(&graph);
-                let pw_event_listener = pw_event_listener.clone();
+                let proxies = Rc::clone(&proxies);
+                let unbound_nodes = Rc::clone(&unbound_nodes);
+                let graph_updates = Rc::clone(&graph_updates);
 
                 move |global| {
                     let registry = registry.clone();
                     let graph = Rc::clone(&graph);
 
                     // Stores only important objects to the algorithm
-                    match global.type_ {
+                    let proxy = match global.type_ {
                         ObjectType::Node => {
-                            registry_global_node(global, registry, graph, pw_event_listener.clone())
+                            registry_global_node(global, graph, Rc::clone(&graph_updates)).map(
+                                |unbound_node| {
+                                    unbound_nodes.borrow_mut().insert(global.id, unbound_node);
+                                    None
+                                },
+                            )
                         }
                         ObjectType::Port => {
-                            registry_global_port(global, registry, graph, pw_event_listener.clone())
+                            registry_global_port(global, graph, Rc::clone(&graph_updates))
+                                .map(|()| None)
                         }
                         ObjectType::Link => {
-                            registry_global_link(global, registry, graph, pw_event_listener.clone())
+                            registry_global_link(global, registry, graph, Rc::clone(&graph_updates))
+                                .map(Some)
                         }
-                        _ => {}
+                        ObjectType::Client => registry_global_client(
+                            global,
+                            registry,
+                            graph,
+                            Rc::clone(&graph_updates),
+                        )
+                        .map(Some),
+                        ObjectType::Device => registry_global_device(
+                            global,
+                            registry,
+                            graph,
+                            Rc::clone(&graph_updates),
+                        )
+                        .map(Some),
+                        ObjectType::Metadata => registry_global_metadata(
+                            global,
+                            registry,
+                            graph,
+                            Rc::clone(&graph_updates),
+                        ),
+                        _ => Ok(None),
+                    };
+
+                    // Objects that can not be tracked are skipped, so that a single odd object
+                    // does not stop the thread
+                    match proxy {
+                        Ok(Some(proxy)) => {
+                            proxies.borrow_mut().insert(global.id, proxy);
+                        }
+                        Ok(None) => {}
+                        Err(err) => warn!(target: "PWConnection", "Skipping object: {err}"),
                     }
                 }
             })
             .global_remove({
                 // Object Removed
                 let graph = Rc::clone(&graph);
-                let pw_event_listener = pw_event_listener.clone();
+                let proxies = Rc::clone(&proxies);
+                let unbound_nodes = Rc::clone(&unbound_nodes);
+                let graph_updates = Rc::clone(&graph_updates);
 
                 move |id| {
-                    registry_global_remove(id, Rc::clone(&graph), pw_event_listener.clone());
+                    proxies.borrow_mut().remove(&id);
+                    unbound_nodes.borrow_mut().remove(&id);
+                    registry_global_remove(id, Rc::clone(&graph), Rc::clone(&graph_updates));
                 }
             })
             .register()
     };
 
-    let _receiver = pw_event_queue.attach(mainloop.loop_(), {
-        let mainloop = mainloop.clone();
+    // Listen to the core done events, that answer roundtrips requested by GraphUpdates, and
+    // evaluate the graph once per burst of changes. Core errors mean the connection was lost.
+    let core_listener = core
+        .add_listener_local()
+        .done({
+            let mainloop = mainloop.clone();
+            let proxies = Rc::clone(&proxies);
+            let graph_updates = Rc::clone(&graph_updates);
 
-        // Treats events sent to the MainLoop thread by the caller
-        move |signal: PWMsg| match signal {
-            PWMsg::Terminate => mainloop.quit(),
-            PWMsg::GraphUpdated => {
-                let should_inhibit_idle = graph.borrow().is_any_sink_active();
-                pw_event_listener
-                    .send(Msg::from(PWEvent::InhibitIdleState(should_inhibit_idle)))
-                    .unwrap();
+            move |id, seq| {
+                if id != PW_ID_CORE || !graph_updates.done(seq) {
+                    return;
+                }
+
+                let active_nodes = graph.borrow_mut().get_active_nodes().clone();
+
+                // Nodes reached for the first time are bound, and the result is only sent once
+                // their info is known, in the next roundtrip
+                if bind_watched_nodes(&registry, &graph, &graph_updates, &proxies, &unbound_nodes) {
+                    graph_updates.mark_dirty();
+                    return;
+                }
+
+                graph_updates.record(TraceEvent::Evaluate);
+                if pw_event_listener
+                    .send(Msg::from(PWEvent::ActiveNodes(remote_id, active_nodes)))
+                    .is_err()
+                {
+                    // The caller is no longer listening, so the connection is dropped and the
+                    // thread stops when it fails to report it
+                    mainloop.quit();
+                }
             }
-        }
-    });
+        })
+        .error({
+            let mainloop = mainloop.clone();
 
-    mainloop.run();
+            move |id, _seq, res, message| {
+                if id == PW_ID_CORE && res == -(Errno::EPIPE as i32) {
+                    mainloop.quit();
+                } else {
+                    warn!(target: "PWConnection", "PipeWire error on object {id}: {message}");
+                }
+            }
+        })
+        .register();
+
+    // Roundtrip that finishes once the initial registry enumeration is done
+    graph_updates.sync();
+
+    Ok(PWConnection {
+        _core_listener: core_listener,
+        _registry_listener: registry_listener,
+        _proxies: proxies,
+    })
 }
 
-/// Handles a new Node object sent by the PipeWire server. Its properties are read and stored in a
-/// [NodeData] object and the object reference is stored in a [Node] [Proxy]. Both are then
-/// inserted into the [PWGraph] shared object.
+/// A Node known only by its registry properties. Its proxy is only bound once its info events may
+/// change the decision, that is, once it is reached by the walk from a watched sink.
+struct UnboundNode {
+    global: GlobalObject<PropertiesBox>,
+    serial: Option<Serial>,
+}
+
+/// Handles a new Node object sent by the PipeWire server. Its registry properties are read and
+/// stored in a [NodeData] object, that is inserted into the [PWGraph] shared object.
 ///
-/// The code also subscribes to updates to that Node.
-fn registry_global_node<Msg: From<PWEvent> + Clone + 'static>(
+/// The Node is not bound yet, and is returned as an [UnboundNode], to be bound by
+/// [bind_watched_nodes] if needed.
+fn registry_global_node(
     node: &GlobalObject<&DictRef>,
-    registry: RegistryRc,
     graph: Rc<RefCell<PWGraph>>,
-    pw_event_listener: MessageQueueSender<Msg>,
-) {
+    graph_updates: Rc<GraphUpdates>,
+) -> Result<UnboundNode, PWError> {
     let id = node.id;
+    let serial = get_serial(node);
     let props = node
         .props
         .as_ref()
-        .expect("Node object is missing properties");
+        .ok_or_else(|| PWError::missing_properties(node))?;
     let name = props.get(&keys::NODE_NAME).map(|s| s.to_string());
     let app_name = props.get(&keys::APP_NAME).map(|s| s.to_string());
     let description = props.get(&keys::NODE_DESCRIPTION).map(|s| s.to_string());
@@ -213,16 +570,9 @@ fn registry_global_node<Msg: From<PWEvent> + Clone + 'static>(
     let media_class = props.get(&keys::MEDIA_CLASS).map(|s| s.to_string());
     let media_role = props.get(&keys::MEDIA_ROLE).map(|s| s.to_string());
     let media_software = props.get(&keys::MEDIA_SOFTWARE).map(|s| s.to_string());
-
-    let proxy: Node = registry.bind(node).expect("Failed to bind Node Proxy");
-    let listener: NodeListener = proxy
-        .add_listener_local()
-        .info({
-            let graph = Rc::clone(&graph);
-            let pw_event_listener = pw_event_listener.clone();
-            move |info| node_info(info, Rc::clone(&graph), pw_event_listener.clone())
-        })
-        .register();
+    let client_id: Option<Id> = props.get(&keys::CLIENT_ID).and_then(|s| s.parse().ok());
+    let device_id: Option<Id> = props.get(&keys::DEVICE_ID).and_then(|s| s.parse().ok());
+    let passive = props.get(&keys::NODE_PASSIVE).map(|s| s.to_string());
 
     let data = NodeData {
         name,
@@ -232,34 +582,163 @@ fn registry_global_node<Msg: From<PWEvent> + Clone + 'static>(
         media_class,
         media_role,
         media_software,
+        client_id,
+        device_id,
+        passive,
+        mute: None,
+        volume: None,
     };
-    graph.borrow_mut().insert(
-        id,
-        PWObject::Node {
-            data,
-            _proxy: Proxy {
-                _proxy: proxy,
-                _listener: listener,
-            },
+    graph_updates.apply(
+        &graph,
+        GraphChange::Insert {
+            id,
+            serial,
+            object: PWObject::Node { data },
         },
     );
 
-    pw_event_listener
-        .send(Msg::from(PWEvent::GraphUpdated))
-        .unwrap();
+    // The properties are not needed to bind the Node later
+    Ok(UnboundNode {
+        global: GlobalObject {
+            id,
+            permissions: node.permissions,
+            type_: node.type_.clone(),
+            version: node.version,
+            props: None,
+        },
+        serial,
+    })
+}
+
+/// Binds the Nodes watched by the [PWGraph] that are not bound yet, storing their [Node] [Proxy]s
+/// to be kept alive. Node info events, that carry every property, and `Props` params are only
+/// received for bound Nodes.
+///
+/// Bound Nodes that are no longer watched are unbound, dropping their [Proxy], and stored as
+/// [UnboundNode]s again, so that the server stops sending their events.
+///
+/// Returns true if any Node was bound, in which case the graph must be evaluated again once their
+/// info is received.
+fn bind_watched_nodes(
+    registry: &RegistryRc,
+    graph: &Rc<RefCell<PWGraph>>,
+    graph_updates: &Rc<GraphUpdates>,
+    proxies: &RefCell<HashMap<Id, PWProxy>>,
+    unbound_nodes: &RefCell<HashMap<Id, UnboundNode>>,
+) -> bool {
+    let watched_nodes = graph.borrow().get_watched_nodes();
+
+    let unwatched_nodes: Vec<Id> = proxies
+        .borrow()
+        .iter()
+        .filter(|(id, proxy)| matches!(proxy, PWProxy::Node(..)) && !watched_nodes.contains(id))
+        .map(|(id, _)| *id)
+        .collect();
+    for id in unwatched_nodes {
+        if let Some(PWProxy::Node(_, unbound_node)) = proxies.borrow_mut().remove(&id) {
+            debug!(target: "bind_watched_nodes", "Unbound Node ({id})");
+            unbound_nodes.borrow_mut().insert(id, unbound_node);
+        }
+    }
+
+    let mut has_bound_nodes = false;
+    for id in watched_nodes {
+        let Some(unbound_node) = unbound_nodes.borrow_mut().remove(&id) else {
+            continue;
+        };
+
+        match bind_node(
+            unbound_node,
+            registry,
+            Rc::clone(graph),
+            Rc::clone(graph_updates),
+        ) {
+            Ok(proxy) => {
+                debug!(target: "bind_watched_nodes", "Bound Node ({id})");
+                proxies.borrow_mut().insert(id, proxy);
+                has_bound_nodes = true;
+            }
+            Err(err) => warn!(target: "bind_watched_nodes", "Skipping Node info: {err}"),
+        }
+    }
+
+    has_bound_nodes
+}
+
+/// Binds a Node, storing its reference in a [Node] [Proxy], that is returned to be kept alive
+/// along with the [UnboundNode].
+///
+/// The code also subscribes to updates to that Node and, if needed, to its `Props` param.
+fn bind_node(
+    unbound_node: UnboundNode,
+    registry: &RegistryRc,
+    graph: Rc<RefCell<PWGraph>>,
+    graph_updates: Rc<GraphUpdates>,
+) -> Result<PWProxy, PWError> {
+    let UnboundNode { global, serial } = &unbound_node;
+    let id = global.id;
+    let serial = *serial;
+
+    let proxy: Node = registry
+        .bind(global)
+        .map_err(|err| PWError::bind(global, err))?;
+    let listener: NodeListener = proxy
+        .add_listener_local()
+        .info({
+            let graph = Rc::clone(&graph);
+            let graph_updates = Rc::clone(&graph_updates);
+            move |info| node_info(info, serial, Rc::clone(&graph), Rc::clone(&graph_updates))
+        })
+        .param({
+            let graph = Rc::clone(&graph);
+            let graph_updates = Rc::clone(&graph_updates);
+            move |_, param_id, _, _, param| {
+                node_param(
+                    id,
+                    serial,
+                    param_id,
+                    param,
+                    Rc::clone(&graph),
+                    Rc::clone(&graph_updates),
+                )
+            }
+        })
+        .register();
+
+    if graph.borrow().watches_volume() {
+        proxy.subscribe_params(&[ParamType::Props]);
+    }
+
+    Ok(PWProxy::Node(
+        Proxy {
+            _proxy: proxy,
+            _listener: listener,
+        },
+        unbound_node,
+    ))
 }
 
 /// Handles updates to already existent [NodeData]. If necessary, the information is updated in the
 /// object in the [PWGraph].
-fn node_info<Msg: From<PWEvent> + Clone>(
+fn node_info(
     info: &NodeInfoRef,
+    serial: Option<Serial>,
     graph: Rc<RefCell<PWGraph>>,
-    pw_event_listener: MessageQueueSender<Msg>,
+    graph_updates: Rc<GraphUpdates>,
 ) {
     let id = info.id();
     debug!("Event Node Info id:{id}");
 
-    let props = info.props().expect("NodeInfo object is missing properties");
+    let Some(props) = info.props() else {
+        warn!(
+            "Skipping info: {}",
+            PWError::MissingProperties {
+                object_type: ObjectType::Node,
+                id
+            }
+        );
+        return;
+    };
     let name = props.get(&keys::NODE_NAME).map(|s| s.to_string());
     let app_name = props.get(&keys::APP_NAME).map(|s| s.to_string());
     let description = props.get(&keys::NODE_DESCRIPTION).map(|s| s.to_string());
@@ -267,6 +746,9 @@ fn node_info<Msg: From<PWEvent> + Clone>(
     let media_class = props.get(&keys::MEDIA_CLASS).map(|s| s.to_string());
     let media_role = props.get(&keys::MEDIA_ROLE).map(|s| s.to_string());
     let media_software = props.get(&keys::MEDIA_SOFTWARE).map(|s| s.to_string());
+    let client_id: Option<Id> = props.get(&keys::CLIENT_ID).and_then(|s| s.parse().ok());
+    let device_id: Option<Id> = props.get(&keys::DEVICE_ID).and_then(|s| s.parse().ok());
+    let passive = props.get(&keys::NODE_PASSIVE).map(|s| s.to_string());
 
     let new_data = NodeData {
         name,
@@ -276,12 +758,75 @@ fn node_info<Msg: From<PWEvent> + Clone>(
         media_class,
         media_role,
         media_software,
+        client_id,
+        device_id,
+        passive,
+        mute: None,
+        volume: None,
     };
-    if graph.borrow_mut().update(id, PWObjectData::Node(new_data)) {
-        pw_event_listener
-            .send(Msg::from(PWEvent::GraphUpdated))
-            .unwrap();
+    graph_updates.apply(
+        &graph,
+        GraphChange::Update {
+            id,
+            serial,
+            data: PWObjectData::Node(new_data),
+        },
+    );
+}
+
+/// Handles the `Props` param of a Node, that holds its mute state and channel volumes. If
+/// necessary, the information is updated in the object in the [PWGraph].
+fn node_param(
+    id: Id,
+    serial: Option<Serial>,
+    param_id: ParamType,
+    param: Option<&Pod>,
+    graph: Rc<RefCell<PWGraph>>,
+    graph_updates: Rc<GraphUpdates>,
+) {
+    if param_id != ParamType::Props {
+        return;
+    }
+    let Some(param) = param else {
+        return;
+    };
+    debug!("Event Node Param Props id:{id}");
+
+    let Ok((_, Value::Object(object))) = PodDeserializer::deserialize_any_from(param.as_bytes())
+    else {
+        warn!("Failed to deserialize Props param of Node {id}");
+        return;
+    };
+
+    let mut new_data = NodeData::default();
+    for property in object.properties {
+        match (property.key, property.value) {
+            (sys::SPA_PROP_mute, Value::Bool(mute)) => new_data.mute = Some(mute),
+            (sys::SPA_PROP_channelVolumes, Value::ValueArray(ValueArray::Float(volumes))) => {
+                new_data.volume = volumes.into_iter().reduce(f32::max);
+            }
+            _ => {}
+        }
     }
+
+    graph_updates.apply(
+        &graph,
+        GraphChange::Update {
+            id,
+            serial,
+            data: PWObjectData::Node(new_data),
+        },
+    );
+}
+
+/// Reads the `object.serial` of a global object, that tells it apart from older objects with the
+/// same id
+fn get_serial(global: &GlobalObject<&DictRef>) -> Option<Serial> {
+    global
+        .props
+        .as_ref()
+        .and_then(|props| props.get(&keys::OBJECT_SERIAL))
+        .and_then(|s| s.parse().ok())
 }
 
 /// Helper function that tries to decode a [Direction] from a string, received from the PipeWire
@@ -295,110 +840,67 @@ fn direction_from_string(direction: &str) -> Option<Direction> {
 }
 
 /// Handles a new Port object sent by the PipeWire server. Its properties are read and stored in a
-/// [PortData] object and the object reference is stored in a [Port] [Proxy]. Both are then
-/// inserted into the [PWGraph] shared object.
+/// [PortData] object, that is inserted into the [PWGraph] shared object.
 ///
-/// The code also subscribes to updates to that Port.
-fn registry_global_port<Msg: From<PWEvent> + Clone + 'static>(
+/// Ports are not bound, as their registry properties already have all the needed data, which does
+/// not change. This avoids a proxy and listener for each of the, possibly hundreds, ports.
+fn registry_global_port(
     port: &GlobalObject<&DictRef>,
-    registry: RegistryRc,
     graph: Rc<RefCell<PWGraph>>,
-    pw_event_listener: MessageQueueSender<Msg>,
-) {
+    graph_updates: Rc<GraphUpdates>,
+) -> Result<(), PWError> {
     let id = port.id;
+    let serial = get_serial(port);
 
     let props = port
         .props
         .as_ref()
-        .expect("Port object is missing properties");
+        .ok_or_else(|| PWError::missing_properties(port))?;
     let name = props.get(&keys::PORT_NAME).map(|s| s.to_string());
     let node_id: Option<Id> = props.get(&keys::NODE_ID).and_then(|s| s.parse().ok());
     let direction = props
         .get(&keys::PORT_DIRECTION)
         .and_then(direction_from_string);
     let is_terminal: Option<bool> = props.get(&keys::PORT_TERMINAL).and_then(|s| s.parse().ok());
-
-    let proxy: Port = registry.bind(port).expect("Failed to bind Port Proxy");
-    let listener: PortListener = proxy
-        .add_listener_local()
-        .info({
-            let graph = Rc::clone(&graph);
-            let pw_event_listener = pw_event_listener.clone();
-            move |info| port_info(info, Rc::clone(&graph), pw_event_listener.clone())
-        })
-        .param(move |_, _param_id, _, _, _param| {}) // TODO
-        .register();
+    let is_monitor: Option<bool> = props.get(&keys::PORT_MONITOR).and_then(|s| s.parse().ok());
 
     let data = PortData {
         name,
         node_id,
         direction,
         is_terminal,
+        is_monitor,
     };
-    graph.borrow_mut().insert(
-        id,
-        PWObject::Port {
-            data,
-            _proxy: Proxy {
-                _proxy: proxy,
-                _listener: listener,
-            },
+    graph_updates.apply(
+        &graph,
+        GraphChange::Insert {
+            id,
+            serial,
+            object: PWObject::Port { data },
         },
     );
 
-    pw_event_listener
-        .send(Msg::from(PWEvent::GraphUpdated))
-        .unwrap();
-}
-
-/// Handles updates to already existent [PortData]. If necessary, the information is updated in the
-/// object in the [PWGraph].
-fn port_info<Msg: From<PWEvent> + Clone>(
-    info: &PortInfoRef,
-    graph: Rc<RefCell<PWGraph>>,
-    pw_event_listener: MessageQueueSender<Msg>,
-) {
-    let id = info.id();
-    debug!("Event Port Info id:{id}");
-
-    let props = info.props().expect("PortInfo object is missing properties");
-    let name = props.get(&keys::PORT_NAME).map(|s| s.to_string());
-    let node_id: Option<Id> = props.get(&keys::NODE_ID).and_then(|s| s.parse().ok());
-    let direction = props
-        .get(&keys::PORT_DIRECTION)
-        .and_then(direction_from_string);
-    let is_terminal: Option<bool> = props.get(&keys::PORT_TERMINAL).and_then(|s| s.parse().ok());
-
-    let new_data = PortData {
-        name,
-        node_id,
-        direction,
-        is_terminal,
-    };
-    if graph.borrow_mut().update(id, PWObjectData::Port(new_data)) {
-        pw_event_listener
-            .send(Msg::from(PWEvent::GraphUpdated))
-            .unwrap();
-    }
+    Ok(())
 }
 
 /// Handles a new Link object sent by the PipeWire server. Its properties are read and stored in a
-/// [LinkData] object and the object reference is stored in a [Link] [Proxy]. Both are then
-/// inserted into the [PWGraph] shared object.
+/// [LinkData] object, that is inserted into the [PWGraph] shared object, and the object
+/// reference is stored in a [Link] [Proxy], that is returned to be kept alive.
 ///
 /// The code also subscribes to updates to that Port.
-fn registry_global_link<Msg: From<PWEvent> + Clone + 'static>(
+fn registry_global_link(
     link: &GlobalObject<&DictRef>,
     registry: RegistryRc,
     graph: Rc<RefCell<PWGraph>>,
-    pw_event_listener: MessageQueueSender<Msg>,
-) {
+    graph_updates: Rc<GraphUpdates>,
+) -> Result<PWProxy, PWError> {
     let id = link.id;
+    let serial = get_serial(link);
 
     let props = link
         .props
         .as_ref()
-        .expect("Port object is missing properties");
+        .ok_or_else(|| PWError::missing_properties(link))?;
 
     let input_port: Option<Id> = props
         .get(&keys::LINK_INPUT_PORT)
@@ -406,15 +908,18 @@ fn registry_global_link<Msg: From<PWEvent> + Clone + 'static>(
     let output_port: Option<Id> = props
         .get(&keys::LINK_OUTPUT_PORT)
         .and_then(|s| s.parse().ok());
+    let passive: Option<bool> = props.get(&keys::LINK_PASSIVE).and_then(|s| s.parse().ok());
     let active = Some(false);
 
-    let proxy: Link = registry.bind(link).expect("Failed to bind Link Proxy");
+    let proxy: Link = registry
+        .bind(link)
+        .map_err(|err| PWError::bind(link, err))?;
     let listener: LinkListener = proxy
         .add_listener_local()
         .info({
             let graph = Rc::clone(&graph);
-            let pw_event_listener = pw_event_listener.clone();
-            move |info| link_info(info, Rc::clone(&graph), pw_event_listener.clone())
+            let graph_updates = Rc::clone(&graph_updates);
+            move |info| link_info(info, serial, Rc::clone(&graph), Rc::clone(&graph_updates))
         })
         .register();
 
@@ -422,21 +927,21 @@ fn registry_global_link<Msg: From<PWEvent> + Clone + 'static>(
         input_port,
         output_port,
         active,
+        passive,
     };
-    graph.borrow_mut().insert(
-        id,
-        PWObject::Link {
-            data,
-            _proxy: Proxy {
-                _proxy: proxy,
-                _listener: listener,
-            },
+    graph_updates.apply(
+        &graph,
+        GraphChange::Insert {
+            id,
+            serial,
+            object: PWObject::Link { data },
         },
     );
 
-    pw_event_listener
-        .send(Msg::from(PWEvent::GraphUpdated))
-        .unwrap();
+    Ok(PWProxy::Link(Proxy {
+        _proxy: proxy,
+        _listener: listener,
+    }))
 }
 
 /// Handles updates to already existent [LinkData]. If necessary, the information is updated in the
@@ -444,20 +949,34 @@ fn registry_global_link<Msg: From<PWEvent> + Clone + 'static>(
 ///
 /// This event is specially important, as the state of the links, stored in the [LinkData::active]
 /// field, is the main information used to search for active clients.
-fn link_info<Msg: From<PWEvent> + Clone>(
+fn link_info(
     info: &LinkInfoRef,
+    serial: Option<Serial>,
     graph: Rc<RefCell<PWGraph>>,
-    pw_event_listener: MessageQueueSender<Msg>,
+    graph_updates: Rc<GraphUpdates>,
 ) {
     let id = info.id();
     debug!("Event Link Info id:{id}");
 
-    let props = info.props().expect("LinkInfo object is missing properties");
+    // The state is still applied if the properties are missing, as it is what matters the most
+    let props = info.props();
+    if props.is_none() {
+        warn!(
+            "Ignoring properties: {}",
+            PWError::MissingProperties {
+                object_type: ObjectType::Link,
+                id
+            }
+        );
+    }
     let input_port: Option<Id> = props
-        .get(&keys::LINK_INPUT_PORT)
+        .and_then(|props| props.get(&keys::LINK_INPUT_PORT))
         .and_then(|s| s.parse().ok());
     let output_port: Option<Id> = props
-        .get(&keys::LINK_OUTPUT_PORT)
+        .and_then(|props| props.get(&keys::LINK_OUTPUT_PORT))
+        .and_then(|s| s.parse().ok());
+    let passive: Option<bool> = props
+        .and_then(|props| props.get(&keys::LINK_PASSIVE))
         .and_then(|s| s.parse().ok());
 
     let active = if info.change_mask().contains(LinkChangeMask::STATE) {
@@ -470,24 +989,299 @@ fn link_info<Msg: From<PWEvent> + Clone>(
         input_port,
         output_port,
         active,
+        passive,
     };
-    if graph.borrow_mut().update(id, PWObjectData::Link(new_data)) {
-        pw_event_listener
-            .send(Msg::from(PWEvent::GraphUpdated))
-            .unwrap();
+    graph_updates.apply(
+        &graph,
+        GraphChange::Update {
+            id,
+            serial,
+            data: PWObjectData::Link(new_data),
+        },
+    );
+}
+
+/// Reads the [ClientData] fields from the properties of a Client
+fn client_data_from_props(props: &DictRef) -> ClientData {
+    ClientData {
+        app_name: props.get(&keys::APP_NAME).map(|s| s.to_string()),
+        process_binary: props.get(&keys::APP_PROCESS_BINARY).map(|s| s.to_string()),
+        app_id: props.get(PORTAL_APP_ID).map(|s| s.to_string()),
     }
 }
 
-/// Handles a removed object from the [PWGraph]
-fn registry_global_remove<Msg: From<PWEvent> + Clone>(
-    id: Id,
+/// Handles a new Client object sent by the PipeWire server. Its properties are read and stored in
+/// a [ClientData] object, that is inserted into the [PWGraph] shared object, and the object
+/// reference is stored in a [Client] [Proxy], that is returned to be kept alive.
+///
+/// The code also subscribes to updates to that Client, as the global object only carries a subset
+/// of its properties.
+fn registry_global_client(
+    client: &GlobalObject<&DictRef>,
+    registry: RegistryRc,
     graph: Rc<RefCell<PWGraph>>,
-    pw_event_listener: MessageQueueSender<Msg>,
+    graph_updates: Rc<GraphUpdates>,
+) -> Result<PWProxy, PWError> {
+    let id = client.id;
+    let serial = get_serial(client);
+
+    let props = client
+        .props
+        .as_ref()
+        .ok_or_else(|| PWError::missing_properties(client))?;
+    let data = client_data_from_props(props);
+
+    let proxy: Client = registry
+        .bind(client)
+        .map_err(|err| PWError::bind(client, err))?;
+    let listener: ClientListener = proxy
+        .add_listener_local()
+        .info({
+            let graph = Rc::clone(&graph);
+            let graph_updates = Rc::clone(&graph_updates);
+            move |info| client_info(info, serial, Rc::clone(&graph), Rc::clone(&graph_updates))
+        })
+        .register();
+
+    graph_updates.apply(
+        &graph,
+        GraphChange::Insert {
+            id,
+            serial,
+            object: PWObject::Client { data },
+        },
+    );
+
+    Ok(PWProxy::Client(Proxy {
+        _proxy: proxy,
+        _listener: listener,
+    }))
+}
+
+/// Handles updates to already existent [ClientData]. If necessary, the information is updated in
+/// the object in the [PWGraph].
+fn client_info(
+    info: &ClientInfoRef,
+    serial: Option<Serial>,
+    graph: Rc<RefCell<PWGraph>>,
+    graph_updates: Rc<GraphUpdates>,
 ) {
-    debug!("Event Registry Global Remove Object id: {id}");
-    graph.borrow_mut().remove(id);
+    let id = info.id();
+    debug!("Event Client Info id:{id}");
+
+    let Some(props) = info.props() else {
+        warn!(
+            "Skipping info: {}",
+            PWError::MissingProperties {
+                object_type: ObjectType::Client,
+                id
+            }
+        );
+        return;
+    };
+    let new_data = client_data_from_props(props);
+    graph_updates.apply(
+        &graph,
+        GraphChange::Update {
+            id,
+            serial,
+            data: PWObjectData::Client(new_data),
+        },
+    );
+}
+
+/// Reads the [DeviceData] fields from the properties of a Device
+fn device_data_from_props(props: &DictRef) -> DeviceData {
+    DeviceData {
+        name: props.get(&keys::DEVICE_NAME).map(|s| s.to_string()),
+        description: props.get(&keys::DEVICE_DESCRIPTION).map(|s| s.to_string()),
+        api: props.get(&keys::DEVICE_API).map(|s| s.to_string()),
+        bus: props.get(&keys::DEVICE_BUS).map(|s| s.to_string()),
+        form_factor: props.get(&keys::DEVICE_FORM_FACTOR).map(|s| s.to_string()),
+    }
+}
+
+/// Handles a new Device object sent by the PipeWire server. Its properties are read and stored in
+/// a [DeviceData] object, that is inserted into the [PWGraph] shared object, and the object
+/// reference is stored in a [Device] [Proxy], that is returned to be kept alive.
+///
+/// The code also subscribes to updates to that Device.
+fn registry_global_device(
+    device: &GlobalObject<&DictRef>,
+    registry: RegistryRc,
+    graph: Rc<RefCell<PWGraph>>,
+    graph_updates: Rc<GraphUpdates>,
+) -> Result<PWProxy, PWError> {
+    let id = device.id;
+    let serial = get_serial(device);
 
-    pw_event_listener
-        .send(Msg::from(PWEvent::GraphUpdated))
-        .unwrap();
+    let props = device
+        .props
+        .as_ref()
+        .ok_or_else(|| PWError::missing_properties(device))?;
+    let data = device_data_from_props(props);
+
+    let proxy: Device = registry
+        .bind(device)
+        .map_err(|err| PWError::bind(device, err))?;
+    let listener: DeviceListener = proxy
+        .add_listener_local()
+        .info({
+            let graph = Rc::clone(&graph);
+            let graph_updates = Rc::clone(&graph_updates);
+            move |info| device_info(info, serial, Rc::clone(&graph), Rc::clone(&graph_updates))
+        })
+        .register();
+
+    graph_updates.apply(
+        &graph,
+        GraphChange::Insert {
+            id,
+            serial,
+            object: PWObject::Device { data },
+        },
+    );
+
+    Ok(PWProxy::Device(Proxy {
+        _proxy: proxy,
+        _listener: listener,
+    }))
+}
+
+/// Handles updates to already existent [DeviceData]. If necessary, the information is updated in
+/// the object in the [PWGraph].
+fn device_info(
+    info: &DeviceInfoRef,
+    serial: Option<Serial>,
+    graph: Rc<RefCell<PWGraph>>,
+    graph_updates: Rc<GraphUpdates>,
+) {
+    let id = info.id();
+    debug!("Event Device Info id:{id}");
+
+    let Some(props) = info.props() else {
+        warn!(
+            "Skipping info: {}",
+            PWError::MissingProperties {
+                object_type: ObjectType::Device,
+                id
+            }
+        );
+        return;
+    };
+    let new_data = device_data_from_props(props);
+    graph_updates.apply(
+        &graph,
+        GraphChange::Update {
+            id,
+            serial,
+            data: PWObjectData::Device(new_data),
+        },
+    );
+}
+
+/// Handles a new Metadata object sent by the PipeWire server. Only the "default" metadata, that
+/// holds the default sink, is tracked. It is inserted into the [PWGraph] shared object, and its
+/// object reference is stored in a [Metadata] [Proxy], that is returned to be kept alive.
+///
+/// The code also subscribes to changes to its properties.
+fn registry_global_metadata(
+    metadata: &GlobalObject<&DictRef>,
+    registry: RegistryRc,
+    graph: Rc<RefCell<PWGraph>>,
+    graph_updates: Rc<GraphUpdates>,
+) -> Result<Option<PWProxy>, PWError> {
+    let id = metadata.id;
+    let serial = get_serial(metadata);
+
+    let name = metadata
+        .props
+        .as_ref()
+        .and_then(|props| props.get(METADATA_NAME));
+    if name != Some(DEFAULT_METADATA_NAME) {
+        return Ok(None);
+    }
+
+    let proxy: Metadata = registry
+        .bind(metadata)
+        .map_err(|err| PWError::bind(metadata, err))?;
+    let listener: MetadataListener = proxy
+        .add_listener_local()
+        .property({
+            let graph = Rc::clone(&graph);
+            let graph_updates = Rc::clone(&graph_updates);
+            move |subject, key, _type, value| {
+                metadata_property(
+                    subject,
+                    key,
+                    value,
+                    Rc::clone(&graph),
+                    Rc::clone(&graph_updates),
+                );
+                0
+            }
+        })
+        .register();
+
+    graph_updates.apply(
+        &graph,
+        GraphChange::Insert {
+            id,
+            serial,
+            object: PWObject::Metadata,
+        },
+    );
+
+    Ok(Some(PWProxy::Metadata(Proxy {
+        _proxy: proxy,
+        _listener: listener,
+    })))
+}
+
+/// Handles changes to the properties of the "default" metadata. If the default sink changed, the
+/// [PWGraph] is updated.
+///
+/// The default sinks are properties of the core object. Properties of other subjects, such as the
+/// `target.object` of streams, are ignored, including when all of them are cleared at once as the
+/// object is removed.
+fn metadata_property(
+    subject: u32,
+    key: Option<&str>,
+    value: Option<&str>,
+    graph: Rc<RefCell<PWGraph>>,
+    graph_updates: Rc<GraphUpdates>,
+) {
+    debug!("Event Metadata Property subject:{subject} key:{key:?} value:{value:?}");
+
+    if subject != PW_ID_CORE {
+        return;
+    }
+
+    let key = match key {
+        Some(DEFAULT_AUDIO_SINK) => DefaultSinkKey::Default,
+        Some(DEFAULT_CONFIGURED_AUDIO_SINK) => DefaultSinkKey::Configured,
+        Some(_) => return,
+        None => DefaultSinkKey::All,
+    };
+
+    let sink_name = value.and_then(|value| {
+        serde_json::from_str::<DefaultNodeValue>(value)
+            .inspect_err(|err| warn!("Failed to parse default sink metadata '{value}': {err}"))
+            .ok()
+            .map(|value| value.name)
+    });
+
+    graph_updates.apply(
+        &graph,
+        GraphChange::DefaultSink {
+            key,
+            name: sink_name,
+        },
+    );
+}
+
+/// Handles a removed object from the [PWGraph]
+fn registry_global_remove(id: Id, graph: Rc<RefCell<PWGraph>>, graph_updates: Rc<GraphUpdates>) {
+    debug!("Event Registry Global Remove Object id: {id}");
+    graph_updates.apply(&graph, GraphChange::Remove { id });
 }
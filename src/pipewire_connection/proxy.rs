@@ -0,0 +1,52 @@
+// Copyright (C) 2024-2026  Rafael Carvalho <contact@rafaelrc.com>
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Proxies of the [pipewire] objects tracked by the [super::graph::PWGraph].
+//!
+//! The proxies are kept apart from the graph, that only holds the object data, so that the graph
+//! may be built and transversed without a connection to the PipeWire server.
+
+use pipewire::{
+    client::{Client, ClientListener},
+    device::{Device, DeviceListener},
+    link::{Link, LinkListener},
+    metadata::{Metadata, MetadataListener},
+    node::{Node, NodeListener},
+    proxy::{Listener, ProxyT},
+};
+
+use super::UnboundNode;
+
+/// Generic struct that joins a [pipewire] [ProxyT], a reference to a global object, and its
+/// respective [Listener].
+pub struct Proxy<TProxy: ProxyT, TListener: Listener> {
+    pub _proxy: TProxy,
+    pub _listener: TListener,
+}
+
+/// Enum of the [Proxy]s of the bound [pipewire] graph elements. They are only stored to keep the
+/// objects bound and their listeners registered.
+///
+/// Ports are never bound, and Nodes are only bound while they are watched, as the registry
+/// properties are enough for the rest. Nodes keep the [UnboundNode] they were bound from, to be
+/// unbound once they are no longer watched.
+pub enum PWProxy {
+    Node(Proxy<Node, NodeListener>, UnboundNode),
+    Link(Proxy<Link, LinkListener>),
+    Client(Proxy<Client, ClientListener>),
+    Device(Proxy<Device, DeviceListener>),
+    Metadata(Proxy<Metadata, MetadataListener>),
+}
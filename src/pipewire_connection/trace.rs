@@ -0,0 +1,155 @@
+// Copyright (C) 2026  Rafael Carvalho <contact@rafaelrc.com>
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Traces of the events handled by the [super::PWThread]s, that may be recorded to a file and
+//! replayed offline, to reproduce the decisions made on another machine.
+//!
+//! A trace is a JSON Lines file, with one [TraceRecord] per line. The PipeWire events are recorded
+//! as the [GraphChange]s they cause, after their properties are read, so that a trace can be
+//! replayed without a connection to the PipeWire server, e.g.
+//!
+//! ```json
+//! {"time":"2026-01-01T12:00:00.000000000+00:00","remote":0,"event":"change","change":"remove","id":42}
+//! ```
+//!
+//! As the raw PipeWire events are not stored, a trace only reproduces the decisions of a build that
+//! reads the same properties from them. Changes to the parsing of the objects are not covered by
+//! replaying traces recorded by an older build.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, FixedOffset, Local};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    RemoteId,
+    graph::{
+        DefaultSinkKey, PWGraph,
+        object::{Id, PWObject, PWObjectData, Serial},
+    },
+};
+
+/// A change to a [PWGraph], caused by a registry global or remove event, an info or param event of
+/// a bound object, or a change to the default sink
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum GraphChange {
+    Insert {
+        id: Id,
+        serial: Option<Serial>,
+        object: PWObject,
+    },
+    Update {
+        id: Id,
+        serial: Option<Serial>,
+        data: PWObjectData,
+    },
+    Remove {
+        id: Id,
+    },
+    DefaultSink {
+        key: DefaultSinkKey,
+        name: Option<String>,
+    },
+}
+
+impl GraphChange {
+    /// Applies the change to a [PWGraph].
+    ///
+    /// Returns true if the graph was changed, and thus must be evaluated again.
+    pub fn apply(self, graph: &mut PWGraph) -> bool {
+        match self {
+            Self::Insert { id, serial, object } => {
+                graph.insert(id, serial, object);
+                true
+            }
+            Self::Update { id, serial, data } => graph.update(id, serial, data),
+            Self::Remove { id } => graph.remove(id).is_some(),
+            Self::DefaultSink { key, name } => graph.set_default_sink(key, name),
+        }
+    }
+}
+
+/// Event of a PipeWire remote stored in a trace
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TraceEvent {
+    Change(Box<GraphChange>),
+    /// The graph was evaluated, and its active nodes sent to the caller
+    Evaluate,
+    /// The connection to the remote was lost. Once it is reconnected, a new graph is built from
+    /// scratch.
+    Disconnected,
+}
+
+/// A line of a trace, with the time the event was handled and the remote it came from. The time
+/// keeps the UTC offset of the recording machine, so that it is replayed in the same time zone.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TraceRecord {
+    pub time: DateTime<FixedOffset>,
+    pub remote: RemoteId,
+    #[serde(flatten)]
+    pub event: TraceEvent,
+}
+
+/// Writes the events of every [super::PWThread] to the same trace file. It is shared between the
+/// threads, and each event is written as a whole line, so that lines are never interleaved.
+///
+/// Writes are buffered, and flushed once the graph is evaluated or the remote is disconnected, so
+/// that a burst of changes is written at once.
+#[derive(Clone)]
+pub struct TraceRecorder {
+    file: Arc<Mutex<BufWriter<File>>>,
+}
+
+impl TraceRecorder {
+    /// Creates the trace file, truncating it if it already exists
+    pub fn create(path: &str) -> io::Result<Self> {
+        Ok(Self {
+            file: Arc::new(Mutex::new(BufWriter::new(File::create(path)?))),
+        })
+    }
+
+    /// Appends an event of a remote to the trace, timestamped with the current time.
+    ///
+    /// Failures are only logged, as the trace is not worth stopping the daemon for.
+    pub fn record(&self, remote: RemoteId, event: TraceEvent) {
+        // The time is read while holding the lock, so that records are written in order
+        let Ok(mut file) = self.file.lock() else {
+            warn!(target: "TraceRecorder::record", "Trace file lock is poisoned");
+            return;
+        };
+        let flush = matches!(event, TraceEvent::Evaluate | TraceEvent::Disconnected);
+        let record = TraceRecord {
+            time: Local::now().fixed_offset(),
+            remote,
+            event,
+        };
+
+        let result = serde_json::to_string(&record)
+            .map_err(io::Error::from)
+            .and_then(|line| writeln!(file, "{line}"))
+            .and_then(|()| if flush { file.flush() } else { Ok(()) });
+        if let Err(err) = result {
+            warn!(target: "TraceRecorder::record", "Failed to record trace event: {err}");
+        }
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright (C) 2026  Rafael Carvalho <contact@rafaelrc.com>
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Offline replay of the traces recorded with `--record`, used to reproduce the decisions made on
+//! another machine.
+//!
+//! The [GraphChange](crate::pipewire_connection::trace::GraphChange)s of each remote are applied to
+//! its own [PWGraph], that is evaluated whenever it was evaluated while recording. The active nodes
+//! are passed to an [InhibitIdleState] running on a [Clock::Virtual], that is moved to the time of
+//! each record, so that the minimum duration, release delay and other timers behave as they did.
+//! Times keep the UTC offset they were recorded with, so that schedule rules are evaluated, and
+//! decisions printed, in the time zone of the recording machine. The decisions are printed as they
+//! are made.
+//!
+//! The trace is only replayed up to its last record. Decisions that would be made later, e.g. once
+//! the minimum duration of media that is still playing is reached, are not printed.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+use chrono::{DateTime, FixedOffset, Local};
+use nix::sys::epoll::{Epoll, EpollCreateFlags};
+
+use crate::inhibit_idle_state::{Clock, InhibitIdleState, InhibitIdleStateEvent};
+use crate::message_queue::{self, MessageQueueReceiver};
+use crate::pipewire_connection::{
+    RemoteId,
+    graph::PWGraph,
+    trace::{TraceEvent, TraceRecord},
+};
+use crate::settings::Settings;
+
+/// Replays the trace at the given path, with the graph and idle inhibiting settings of the
+/// current configuration, which may differ from the ones used while recording.
+pub fn replay(settings: &Settings, path: &str) -> Result<(), Box<dyn Error>> {
+    let trace = File::open(path).map_err(|err| format!("Failed to open trace '{path}': {err}"))?;
+
+    let epoll = Epoll::new(EpollCreateFlags::empty())?;
+    let (mq, mq_receiver) = message_queue::message_queue::<InhibitIdleStateEvent>(&epoll, 0)?;
+
+    // The clock is moved to the time of the first record before any decision is made
+    let mut inhibit_idle_state_manager = InhibitIdleState::new(
+        settings.get_media_minimum_duration(),
+        settings.get_media_release_delay(),
+        settings.get_minimum_toggle_interval(),
+        settings.get_media_maximum_duration(),
+        settings.get_schedule().clone(),
+        Clock::Virtual(Local::now().fixed_offset()),
+        mq,
+    );
+
+    let graph_config = settings.get_graph_config();
+    let mut graphs: HashMap<RemoteId, PWGraph> = HashMap::new();
+
+    for (line_number, line) in BufReader::new(trace).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let TraceRecord {
+            time,
+            remote,
+            event,
+        } = serde_json::from_str(&line).map_err(|err| {
+            format!(
+                "Invalid record at line {} of trace '{path}': {err}",
+                line_number + 1
+            )
+        })?;
+
+        // Updates scheduled before the record are made at the time they were due
+        while let Some(next_update) = inhibit_idle_state_manager
+            .get_next_update()
+            .map(|next_update| next_update.with_timezone(time.offset()))
+            .filter(|next_update| *next_update <= time)
+        {
+            inhibit_idle_state_manager.set_time(next_update);
+            inhibit_idle_state_manager.timer_expired();
+            print_decisions(next_update, &mq_receiver)?;
+        }
+        inhibit_idle_state_manager.set_time(time);
+
+        match event {
+            TraceEvent::Change(change) => {
+                let graph = graphs
+                    .entry(remote)
+                    .or_insert_with(|| PWGraph::new(graph_config.clone()));
+                change.apply(graph);
+            }
+            TraceEvent::Evaluate => {
+                let graph = graphs
+                    .entry(remote)
+                    .or_insert_with(|| PWGraph::new(graph_config.clone()));
+                inhibit_idle_state_manager
+                    .set_active_nodes(remote, graph.get_active_nodes().clone());
+            }
+            TraceEvent::Disconnected => {
+                graphs.remove(&remote);
+                inhibit_idle_state_manager.disconnected(remote);
+            }
+        }
+        print_decisions(time, &mq_receiver)?;
+    }
+
+    Ok(())
+}
+
+/// Prints the decisions sent by the [InhibitIdleState] since the last call, made at the given time
+fn print_decisions(
+    time: DateTime<FixedOffset>,
+    mq_receiver: &MessageQueueReceiver<InhibitIdleStateEvent>,
+) -> Result<(), Box<dyn Error>> {
+    let time = time.format("%Y-%m-%d %H:%M:%S%.3f");
+    while let Some(event) = mq_receiver.try_recv()? {
+        match event {
+            InhibitIdleStateEvent::InhibitIdle(true) => println!("{time} Idle is inhibited"),
+            InhibitIdleStateEvent::InhibitIdle(false) => {
+                println!("{time} Idle is no longer inhibited")
+            }
+            InhibitIdleStateEvent::ActivePaths(active_paths) => {
+                if active_paths.is_empty() {
+                    println!("{time} No active paths");
+                }
+                for path in active_paths {
+                    println!("{time} Active path: {path}");
+                }
+            }
+            // Only sent by the timer of the system clock
+            InhibitIdleStateEvent::TimerExpired => {}
+        }
+    }
+    Ok(())
+}
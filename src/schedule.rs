@@ -0,0 +1,138 @@
+// Copyright (C) 2026  Rafael Carvalho <contact@rafaelrc.com>
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Time-of-day and weekday rules that change how the idle inhibit state is decided.
+
+use chrono::{
+    DateTime, Datelike, Days, Duration, Local, LocalResult, NaiveDateTime, NaiveTime, TimeZone,
+    Weekday,
+};
+use serde::Deserialize;
+use serde_with::{DisplayFromStr, serde_as};
+
+/// A rule that is active on the given days, between the given times.
+///
+/// When `to` is before `from`, the rule wraps around midnight. Days are always matched against the
+/// current day.
+#[serde_as]
+#[derive(Deserialize, Clone, Debug)]
+pub struct ScheduleRule {
+    #[serde(default)]
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    days: Vec<Weekday>,
+
+    #[serde(default)]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    from: Option<NaiveTime>,
+
+    #[serde(default)]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    to: Option<NaiveTime>,
+
+    /// If set, idle is always (true) or never (false) inhibited while the rule is active
+    #[serde(default)]
+    inhibit: Option<bool>,
+
+    /// If set, defines if the node blacklist is honoured while the rule is active
+    #[serde(default)]
+    node_blacklist: Option<bool>,
+}
+
+impl ScheduleRule {
+    /// Checks if the rule is active at a given local date and time
+    fn is_active_at(&self, now: &NaiveDateTime) -> bool {
+        if !self.days.is_empty() && !self.days.contains(&now.weekday()) {
+            return false;
+        }
+
+        let time = now.time();
+        let from = self.from.unwrap_or(NaiveTime::MIN);
+        match self.to {
+            None => time >= from,
+            Some(to) if from <= to => time >= from && time < to,
+            Some(to) => time >= from || time < to,
+        }
+    }
+
+    /// Returns the local times, today and tomorrow, after `now` at which the rule may become
+    /// active or inactive
+    fn boundaries(&self, now: &NaiveDateTime) -> impl Iterator<Item = NaiveDateTime> + '_ {
+        let today = now.date();
+        let tomorrow = today.checked_add_days(Days::new(1));
+        let now = *now;
+
+        [Some(today), tomorrow]
+            .into_iter()
+            .flatten()
+            .flat_map(|date| {
+                [Some(NaiveTime::MIN), self.from, self.to]
+                    .into_iter()
+                    .flatten()
+                    .map(move |time| date.and_time(time))
+            })
+            .filter(move |boundary| *boundary > now)
+    }
+}
+
+/// Effects of the schedule rules at a given instant
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScheduleState {
+    pub inhibit: Option<bool>,
+    pub node_blacklist: bool,
+    pub next_change: Option<DateTime<Local>>,
+}
+
+/// Evaluates the schedule rules at a given instant, in the time zone it is given in. When more than
+/// one active rule sets the same option, the last one in the list takes precedence.
+pub fn schedule_state_at<Tz: TimeZone>(rules: &[ScheduleRule], now: DateTime<Tz>) -> ScheduleState {
+    let time_zone = now.timezone();
+    let naive_now = now.naive_local();
+    let mut state = ScheduleState {
+        inhibit: None,
+        node_blacklist: true,
+        next_change: None,
+    };
+
+    for rule in rules {
+        if rule.is_active_at(&naive_now) {
+            state.inhibit = rule.inhibit.or(state.inhibit);
+            state.node_blacklist = rule.node_blacklist.unwrap_or(state.node_blacklist);
+        }
+    }
+
+    state.next_change = rules
+        .iter()
+        .flat_map(|rule| rule.boundaries(&naive_now))
+        .filter_map(|boundary| match time_zone.from_local_datetime(&boundary) {
+            LocalResult::Single(boundary) => Some(boundary),
+            // Local times repeated when daylight saving time ends happen twice, and the first
+            // one may already be past
+            LocalResult::Ambiguous(earliest, latest) => {
+                Some(if earliest > now { earliest } else { latest })
+            }
+            // Boundaries skipped by a daylight saving time change happen an hour later
+            LocalResult::None => time_zone
+                .from_local_datetime(&(boundary + Duration::hours(1)))
+                .earliest(),
+        })
+        // Only boundaries strictly after now are taken, so that the state is never evaluated
+        // again at the same instant
+        .filter(|boundary| *boundary > now)
+        .min()
+        .map(|boundary| boundary.with_timezone(&Local));
+
+    state
+}
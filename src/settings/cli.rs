@@ -1,4 +1,4 @@
-// Copyright (C) 2024  Rafael Carvalho <contact@rafaelrc.com>
+// Copyright (C) 2024-2026  Rafael Carvalho <contact@rafaelrc.com>
 
 // This program is free software: you can redistribute it and/or modify
 // it under the terms of the GNU General Public License version 3 as published by
@@ -18,7 +18,7 @@
 
 use std::fmt::Display;
 
-use clap::{Parser, ValueEnum, builder::PossibleValue};
+use clap::{Parser, Subcommand, ValueEnum, builder::PossibleValue};
 use log::LevelFilter;
 use serde::{Deserialize, Serialize};
 use serde_with::{NoneAsEmptyString, serde_as};
@@ -42,6 +42,36 @@ pub struct Args {
     #[serde(skip_serializing_if = "::std::option::Option::is_none")]
     media_minimum_duration: Option<i64>,
 
+    #[arg(
+        short = 'r',
+        long,
+        value_name = "SECONDS",
+        allow_negative_numbers = false,
+        help = format!("Time media must be silent before idle is uninhibited [default: {}]", super::default_media_release_delay())
+    )]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    media_release_delay: Option<i64>,
+
+    #[arg(
+        short = 't',
+        long,
+        value_name = "SECONDS",
+        allow_negative_numbers = false,
+        help = format!("Minimum time between idle inhibitor state changes [default: {}]", super::default_minimum_toggle_interval())
+    )]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    minimum_toggle_interval: Option<i64>,
+
+    #[arg(
+        short = 'm',
+        long,
+        value_name = "SECONDS",
+        allow_negative_numbers = false,
+        help = format!("Maximum time idle may be continuously inhibited, 0 means no limit [default: {}]", super::default_media_maximum_duration())
+    )]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    media_maximum_duration: Option<i64>,
+
     #[arg(
         short,
         long,
@@ -106,9 +136,48 @@ pub struct Args {
     #[serde(default, skip_serializing)]
     dry_run: bool,
 
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "PipeWire remote to connect to, may be repeated to watch several remotes [default: the default remote]"
+    )]
+    #[serde(skip_serializing_if = "::std::vec::Vec::is_empty")]
+    pipewire_remote: Vec<String>,
+
     #[arg(short, long, value_name = "PATH", help = "Path to config file")]
     #[serde(default, skip_serializing)]
     pub config: Option<String>,
+
+    #[arg(
+        long,
+        help = "Lists the built-in node blacklist presets and the filters they expand to"
+    )]
+    #[serde(default, skip_serializing)]
+    pub list_presets: bool,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Records the PipeWire events to a trace file, that may be replayed with the replay command. The events are stored as the graph changes they cause, so the trace is only replayed faithfully by a build that parses PipeWire objects the same way"
+    )]
+    #[serde(default, skip_serializing)]
+    pub record: Option<String>,
+
+    #[command(subcommand)]
+    #[serde(skip)]
+    pub command: Option<Command>,
+}
+
+/// Commands that are run instead of the daemon
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(
+        about = "Replays a trace recorded with --record offline, printing the idle inhibitor decisions over time"
+    )]
+    Replay {
+        #[arg(value_name = "PATH", help = "Path to the trace file")]
+        trace: String,
+    },
 }
 
 /// Wrapper type around [LevelFilter] to implement the trait [ValueEnum] for better CLI args
@@ -24,13 +24,18 @@ use figment::{
     providers::{Format, Serialized, Toml},
 };
 use log::{LevelFilter, warn};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use serde_with::{DisplayFromStr, serde_as};
+use serde_with::{DisplayFromStr, OneOrMany, serde_as};
 
-use crate::pipewire_connection::graph::filter::{NodeFilter, SinkFilter};
+use crate::pipewire_connection::graph::{
+    GraphConfig,
+    filter::{NodeFilter, Preset, RouteFilter, SinkFilter},
+};
+use crate::schedule::ScheduleRule;
 
 mod cli;
-use cli::Args;
+use cli::{Args, Command};
 
 /// Struct that stores the settings that affect the tool behaviour
 #[serde_as]
@@ -39,18 +44,76 @@ pub struct Settings {
     #[serde(default = "default_media_minimum_duration")]
     media_minimum_duration: i64,
 
+    #[serde(default = "default_media_release_delay")]
+    media_release_delay: i64,
+
+    #[serde(default = "default_minimum_toggle_interval")]
+    minimum_toggle_interval: i64,
+
+    #[serde(default = "default_media_maximum_duration")]
+    media_maximum_duration: i64,
+
+    #[serde(default)]
+    rearm_on_user_input: bool,
+
     #[serde(default = "default_idle_inhibitor")]
     #[serde_as(as = "DisplayFromStr")]
     idle_inhibitor: IdleInhibitor,
 
+    #[serde(default = "default_dbus_reason")]
+    dbus_reason: String,
+
+    #[serde(default)]
+    dbus_inhibit_per_app: bool,
+
     #[serde(default = "default_verbosity")]
     verbosity: LevelFilter,
 
+    #[serde(default)]
+    #[serde_as(as = "OneOrMany<_>")]
+    pipewire_remote: Vec<String>,
+
     #[serde(default)]
     sink_whitelist: Vec<SinkFilter>,
 
     #[serde(default)]
     node_blacklist: Vec<NodeFilter>,
+
+    #[serde(default)]
+    presets: Vec<Preset>,
+
+    #[serde(default)]
+    route_blacklist: Vec<RouteFilter>,
+
+    #[serde(default)]
+    schedule: Vec<ScheduleRule>,
+
+    #[serde(default = "default_client_media_classes", with = "serde_regex")]
+    client_media_classes: Vec<Regex>,
+
+    #[serde(default)]
+    detect_recording: bool,
+
+    #[serde(default)]
+    recorder_whitelist: Vec<NodeFilter>,
+
+    #[serde(default = "default_ignore_passive")]
+    ignore_passive: bool,
+
+    #[serde(default)]
+    ignore_muted: bool,
+
+    #[serde(default = "default_minimum_volume")]
+    minimum_volume: f32,
+
+    #[serde(skip)]
+    list_presets: bool,
+
+    #[serde(skip)]
+    record: Option<String>,
+
+    #[serde(skip)]
+    replay: Option<String>,
 }
 
 impl Settings {
@@ -63,10 +126,19 @@ impl Settings {
                 .place_config_file("config.toml")?,
         };
 
-        let settings = Figment::new()
+        let list_presets = cli.list_presets;
+        let record = cli.record.clone();
+        let replay = match cli.command {
+            Some(Command::Replay { ref trace }) => Some(trace.clone()),
+            None => None,
+        };
+        let mut settings: Self = Figment::new()
             .merge(Toml::file(config_path))
             .merge(Serialized::defaults(cli))
             .extract()?;
+        settings.list_presets = list_presets;
+        settings.record = record;
+        settings.replay = replay;
 
         Ok(settings)
     }
@@ -74,15 +146,30 @@ impl Settings {
     /// Getter for the media minimum duration with the [chrono::Duration] type. If the set duration
     /// is 0, [None] is returned, to easily detect if this check is necessary
     pub fn get_media_minimum_duration(&self) -> Option<Duration> {
-        match self.media_minimum_duration.cmp(&0) {
-            Ordering::Less => {
-                warn!(target: "Settings::get_media_minimum_duration",
-                    "Tried to use a negative value as media minimum duration! Assuming as zero.");
-                None
-            }
-            Ordering::Equal => None,
-            Ordering::Greater => Some(Duration::seconds(self.media_minimum_duration)),
-        }
+        seconds_to_duration(self.media_minimum_duration, "media minimum duration")
+    }
+
+    /// Getter for the time media must be silent before idle is uninhibited. If the set duration is
+    /// 0, [None] is returned
+    pub fn get_media_release_delay(&self) -> Option<Duration> {
+        seconds_to_duration(self.media_release_delay, "media release delay")
+    }
+
+    /// Getter for the minimum time between two idle inhibitor state changes. If the set duration
+    /// is 0, [None] is returned
+    pub fn get_minimum_toggle_interval(&self) -> Option<Duration> {
+        seconds_to_duration(self.minimum_toggle_interval, "minimum toggle interval")
+    }
+
+    /// Getter for the maximum time idle may be continuously inhibited. If the set duration is 0,
+    /// [None] is returned, meaning there is no limit
+    pub fn get_media_maximum_duration(&self) -> Option<Duration> {
+        seconds_to_duration(self.media_maximum_duration, "media maximum duration")
+    }
+
+    /// Returns if user input should re-arm the idle inhibitor after the maximum duration
+    pub fn get_rearm_on_user_input(&self) -> bool {
+        self.rearm_on_user_input
     }
 
     /// Returns the current log verbosity
@@ -90,19 +177,129 @@ impl Settings {
         self.verbosity
     }
 
+    /// Returns the names of the PipeWire remotes to connect to. If empty, only the default remote
+    /// is used
+    pub fn get_pipewire_remotes(&self) -> &Vec<String> {
+        &self.pipewire_remote
+    }
+
     /// Return sink filters
     pub fn get_sink_whitelist(&self) -> &Vec<SinkFilter> {
         &self.sink_whitelist
     }
 
-    /// Return Node filters
-    pub fn get_node_blacklist(&self) -> &Vec<NodeFilter> {
-        &self.node_blacklist
+    /// Return Node filters, including the ones of the enabled presets
+    pub fn get_node_blacklist(&self) -> Vec<NodeFilter> {
+        self.node_blacklist
+            .iter()
+            .cloned()
+            .chain(self.presets.iter().flat_map(Preset::filters))
+            .collect()
+    }
+
+    /// Returns if the presets should be listed instead of running the program
+    pub fn get_list_presets(&self) -> bool {
+        self.list_presets
+    }
+
+    /// Returns the path of the file the PipeWire events should be recorded to, if any
+    pub fn get_record(&self) -> Option<&str> {
+        self.record.as_deref()
+    }
+
+    /// Returns the path of the trace that should be replayed instead of running the program, if
+    /// any
+    pub fn get_replay(&self) -> Option<&str> {
+        self.replay.as_deref()
+    }
+
+    /// Return Route filters
+    pub fn get_route_blacklist(&self) -> &Vec<RouteFilter> {
+        &self.route_blacklist
+    }
+
+    /// Return media classes of client nodes
+    pub fn get_client_media_classes(&self) -> &Vec<Regex> {
+        &self.client_media_classes
+    }
+
+    /// Returns if nodes recording sinks should inhibit idle
+    pub fn get_detect_recording(&self) -> bool {
+        self.detect_recording
+    }
+
+    /// Return recorder filters
+    pub fn get_recorder_whitelist(&self) -> &Vec<NodeFilter> {
+        &self.recorder_whitelist
+    }
+
+    /// Returns if passive links and nodes should be ignored
+    pub fn get_ignore_passive(&self) -> bool {
+        self.ignore_passive
+    }
+
+    /// Returns if muted nodes should be ignored
+    pub fn get_ignore_muted(&self) -> bool {
+        self.ignore_muted
+    }
+
+    /// Getter for the minimum volume, in percent, of nodes. If the set volume is 0, [None] is
+    /// returned, meaning volume is not checked
+    pub fn get_minimum_volume(&self) -> Option<f32> {
+        if self.minimum_volume < 0.0 {
+            warn!(target: "Settings::get_minimum_volume",
+                "Tried to use a negative value as minimum volume! Assuming as zero.");
+        }
+        (self.minimum_volume > 0.0).then_some(self.minimum_volume)
+    }
+
+    /// Builds the [GraphConfig] used to walk the PipeWire graphs
+    pub fn get_graph_config(&self) -> GraphConfig {
+        GraphConfig {
+            sink_whitelist: self.get_sink_whitelist().to_vec(),
+            node_blacklist: self.get_node_blacklist(),
+            route_blacklist: self.get_route_blacklist().to_vec(),
+            client_media_classes: self.get_client_media_classes().to_vec(),
+            detect_recording: self.get_detect_recording(),
+            recorder_whitelist: self.get_recorder_whitelist().to_vec(),
+            ignore_passive: self.get_ignore_passive(),
+            ignore_muted: self.get_ignore_muted(),
+            minimum_volume: self.get_minimum_volume(),
+        }
+    }
+
+    /// Return schedule rules
+    pub fn get_schedule(&self) -> &Vec<ScheduleRule> {
+        &self.schedule
     }
 
     pub fn get_idle_inhibitor(&self) -> &IdleInhibitor {
         &self.idle_inhibitor
     }
+
+    /// Returns the template of the reason sent by the D-Bus idle inhibitor
+    pub fn get_dbus_reason(&self) -> &str {
+        &self.dbus_reason
+    }
+
+    /// Returns if the D-Bus idle inhibitor should hold one inhibition per playing application
+    pub fn get_dbus_inhibit_per_app(&self) -> bool {
+        self.dbus_inhibit_per_app
+    }
+}
+
+/// Converts a setting in seconds to a [chrono::Duration]. Zero is converted to [None] and negative
+/// values are treated as zero.
+fn seconds_to_duration(seconds: i64, setting_name: &str) -> Option<Duration> {
+    match seconds.cmp(&0) {
+        Ordering::Less => {
+            warn!(target: "Settings::seconds_to_duration",
+                "Tried to use a negative value as {setting_name}! Assuming as zero.");
+            None
+        }
+        Ordering::Equal => None,
+        Ordering::Greater => Some(Duration::seconds(seconds)),
+    }
 }
 
 /// Default media minimum duration, set to 5 seconds
@@ -110,6 +307,36 @@ const fn default_media_minimum_duration() -> i64 {
     5
 }
 
+/// Default media release delay, set to 0 seconds (release immediately)
+const fn default_media_release_delay() -> i64 {
+    0
+}
+
+/// Default minimum toggle interval, set to 0 seconds (no limit)
+const fn default_minimum_toggle_interval() -> i64 {
+    0
+}
+
+/// Default media maximum duration, set to 0 seconds (no limit)
+const fn default_media_maximum_duration() -> i64 {
+    0
+}
+
+/// Default media classes of client nodes, set to all output streams
+fn default_client_media_classes() -> Vec<Regex> {
+    vec![Regex::new("^Stream/Output/").unwrap()]
+}
+
+/// Default for ignoring passive links and nodes, set to true
+const fn default_ignore_passive() -> bool {
+    true
+}
+
+/// Default minimum volume, set to 0% (volume is not checked)
+const fn default_minimum_volume() -> f32 {
+    0.0
+}
+
 /// Default log verbosity, set to [LevelFilter::Warn]
 const fn default_verbosity() -> LevelFilter {
     LevelFilter::Warn
@@ -120,6 +347,11 @@ const fn default_idle_inhibitor() -> IdleInhibitor {
     IdleInhibitor::Wayland
 }
 
+/// Default template of the D-Bus inhibit reason, listing the playing apps and their sinks
+fn default_dbus_reason() -> String {
+    String::from("{app_names} playing on {sinks}")
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ValueEnum)]
 pub enum IdleInhibitor {
     DBus,
@@ -0,0 +1,166 @@
+// Copyright (C) 2026  Rafael Carvalho <contact@rafaelrc.com>
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Builder of synthetic [PWGraph]s, shared by the graph tests and benchmarks, that include it
+//! with `#[path]` next to the graph module.
+
+use std::collections::{BTreeSet, HashMap};
+
+use regex::Regex;
+
+use crate::graph::{
+    ActiveNode, GraphConfig, PWGraph,
+    object::{Direction, Id, LinkData, NodeData, PWObject, PWObjectData, PortData, Serial},
+};
+
+/// The default [GraphConfig], as built from an empty config file
+pub fn config() -> GraphConfig {
+    GraphConfig {
+        sink_whitelist: Vec::new(),
+        node_blacklist: Vec::new(),
+        route_blacklist: Vec::new(),
+        client_media_classes: vec![Regex::new("^Stream/Output/").unwrap()],
+        detect_recording: false,
+        recorder_whitelist: Vec::new(),
+        ignore_passive: true,
+        ignore_muted: false,
+        minimum_volume: None,
+    }
+}
+
+/// A [PWGraph] built from synthetic objects
+pub struct TestGraph {
+    pub graph: PWGraph,
+    /// Last id given to an object, that is also the number of objects
+    pub next_id: Id,
+}
+
+impl TestGraph {
+    pub fn new(config: GraphConfig) -> Self {
+        Self {
+            graph: PWGraph::new(config),
+            next_id: 0,
+        }
+    }
+
+    pub fn next_id(&mut self) -> Id {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    pub fn insert_node(&mut self, id: Id, data: NodeData) {
+        self.graph.insert(id, None, PWObject::Node { data });
+    }
+
+    pub fn insert_port(&mut self, id: Id, node_id: Id, direction: Direction) {
+        let data = PortData {
+            name: None,
+            node_id: Some(node_id),
+            direction: Some(direction),
+            is_terminal: None,
+            is_monitor: Some(false),
+        };
+        self.graph.insert(id, None, PWObject::Port { data });
+    }
+
+    pub fn insert_link(&mut self, id: Id, output_port: Id, input_port: Id) {
+        let data = LinkData {
+            input_port: Some(input_port),
+            output_port: Some(output_port),
+            active: Some(true),
+            passive: Some(false),
+        };
+        self.graph.insert(id, None, PWObject::Link { data });
+    }
+
+    pub fn add_node(&mut self, name: &str, media_class: Option<&str>) -> Id {
+        self.add_node_data(NodeData {
+            name: Some(String::from(name)),
+            media_class: media_class.map(String::from),
+            ..Default::default()
+        })
+    }
+
+    pub fn add_node_data(&mut self, data: NodeData) -> Id {
+        let id = self.next_id();
+        self.insert_node(id, data);
+        id
+    }
+
+    pub fn add_port(&mut self, node_id: Id, direction: Direction) -> Id {
+        let id = self.next_id();
+        self.insert_port(id, node_id, direction);
+        id
+    }
+
+    pub fn add_link(&mut self, output_port: Id, input_port: Id) -> Id {
+        let id = self.next_id();
+        self.insert_link(id, output_port, input_port);
+        id
+    }
+
+    /// Links an output port of a node to an input port of another, adding both ports. Returns the
+    /// id of the link.
+    pub fn connect(&mut self, from: Id, to: Id) -> Id {
+        let output_port = self.add_port(from, Direction::Output);
+        let input_port = self.add_port(to, Direction::Input);
+        self.add_link(output_port, input_port)
+    }
+
+    pub fn update_node(&mut self, id: Id, data: NodeData) -> bool {
+        self.graph.update(id, None, PWObjectData::Node(data))
+    }
+
+    pub fn update_link(&mut self, id: Id, serial: Option<Serial>, data: LinkData) -> bool {
+        self.graph.update(id, serial, PWObjectData::Link(data))
+    }
+
+    /// Sets a link as active or inactive, as when a stream is paused or resumed
+    pub fn set_link_active(&mut self, id: Id, active: bool) -> bool {
+        self.update_link(id, None, link_state(Some(active), None))
+    }
+
+    /// Evaluates the graph, returning the active nodes
+    pub fn active_nodes(&mut self) -> HashMap<Id, ActiveNode> {
+        self.graph.get_active_nodes().clone()
+    }
+
+    /// Evaluates the graph, returning only the ids of the active nodes
+    pub fn active_ids(&mut self) -> BTreeSet<Id> {
+        self.active_nodes().into_keys().collect()
+    }
+
+    /// Evaluates the graph, returning the paths to an active node
+    pub fn paths(&mut self, id: Id) -> Vec<Vec<String>> {
+        let mut paths: Vec<Vec<String>> = self.active_nodes()[&id]
+            .paths
+            .iter()
+            .map(|path| path.nodes.clone())
+            .collect();
+        paths.sort();
+        paths
+    }
+}
+
+/// Link data that only changes the state of the link
+pub fn link_state(active: Option<bool>, passive: Option<bool>) -> LinkData {
+    LinkData {
+        input_port: None,
+        output_port: None,
+        active,
+        passive,
+    }
+}
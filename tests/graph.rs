@@ -0,0 +1,938 @@
+// Copyright (C) 2026  Rafael Carvalho <contact@rafaelrc.com>
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Tests of the [graph::PWGraph] walk over synthetic topologies, built without a connection to the
+//! PipeWire server.
+//!
+//! Objects are inserted, updated and removed as the PipeWire events would do, in any order, and
+//! the graph is evaluated between the changes, so that the cached activity of the sinks is also
+//! checked.
+//!
+//! Run with `cargo test`.
+
+use std::collections::BTreeSet;
+
+use serde::de::DeserializeOwned;
+
+#[allow(dead_code)]
+#[path = "../src/pipewire_connection/graph/mod.rs"]
+mod graph;
+use graph::{
+    DefaultSinkKey,
+    filter::Preset,
+    object::{
+        ClientData, DeviceData, Direction, Id, LinkData, NodeData, PWObject, PWObjectData, PortData,
+    },
+};
+
+#[allow(dead_code)]
+#[path = "common/mod.rs"]
+mod common;
+use common::{TestGraph, config, link_state};
+
+/// Parses a list of filters, written as they would be in the config file, but in JSON
+fn filters<T: DeserializeOwned>(json: &str) -> Vec<T> {
+    serde_json::from_str(json).unwrap()
+}
+
+fn ids<const N: usize>(ids: [Id; N]) -> BTreeSet<Id> {
+    BTreeSet::from(ids)
+}
+
+fn path(nodes: &[&str]) -> Vec<String> {
+    nodes.iter().map(|node| String::from(*node)).collect()
+}
+
+#[test]
+fn client_playing_into_sink() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let client = g.add_node("music", Some("Stream/Output/Audio"));
+    g.connect(client, sink);
+
+    let active_nodes = g.active_nodes();
+    assert_eq!(active_nodes.keys().copied().collect::<Vec<_>>(), [client]);
+    assert!(!active_nodes[&client].is_blacklisted);
+    assert_eq!(g.paths(client), [path(&["music", "speakers"])]);
+}
+
+#[test]
+fn graph_without_sinks_has_no_active_nodes() {
+    let mut g = TestGraph::new(config());
+    let source = g.add_node("microphone", Some("Audio/Source"));
+    let client = g.add_node("music", Some("Stream/Output/Audio"));
+    g.connect(client, source);
+
+    assert!(g.active_nodes().is_empty());
+}
+
+#[test]
+fn chain_of_filters() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let equalizer = g.add_node("equalizer", Some("Stream/Input/Audio"));
+    let effect = g.add_node("effect", None);
+    let client = g.add_node("music", Some("Stream/Output/Audio"));
+    g.connect(equalizer, sink);
+    g.connect(effect, equalizer);
+    g.connect(client, effect);
+
+    assert_eq!(g.active_ids(), ids([client]));
+    assert_eq!(
+        g.paths(client),
+        [path(&["music", "effect", "equalizer", "speakers"])]
+    );
+}
+
+#[test]
+fn node_without_media_class_nor_input_ports_is_a_client() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let source = g.add_node("microphone", Some("Audio/Source"));
+    let tone = g.add_node("tone", None);
+    g.connect(source, sink);
+    g.connect(tone, sink);
+
+    assert_eq!(g.active_ids(), ids([tone]));
+}
+
+#[test]
+fn several_clients_and_sinks() {
+    let mut g = TestGraph::new(config());
+    let speakers = g.add_node("speakers", Some("Audio/Sink"));
+    let headphones = g.add_node("headphones", Some("Audio/Sink"));
+    let music = g.add_node("music", Some("Stream/Output/Audio"));
+    let video = g.add_node("video", Some("Stream/Output/Audio"));
+    g.connect(music, speakers);
+    g.connect(music, headphones);
+    g.connect(video, headphones);
+
+    assert_eq!(g.active_ids(), ids([music, video]));
+    assert_eq!(
+        g.paths(music),
+        [path(&["music", "headphones"]), path(&["music", "speakers"])]
+    );
+}
+
+#[test]
+fn inactive_links_are_not_followed() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let paused = g.add_node("paused", Some("Stream/Output/Audio"));
+    let link = g.connect(paused, sink);
+    g.set_link_active(link, false);
+
+    // Links are registered before their state is known
+    let unknown = g.add_node("unknown", Some("Stream/Output/Audio"));
+    let output_port = g.add_port(unknown, Direction::Output);
+    let input_port = g.add_port(sink, Direction::Input);
+    let id = g.next_id();
+    let data = LinkData {
+        input_port: Some(input_port),
+        output_port: Some(output_port),
+        active: None,
+        passive: None,
+    };
+    g.graph.insert(id, None, PWObject::Link { data });
+
+    assert!(g.active_nodes().is_empty());
+}
+
+#[test]
+fn link_state_changes_update_cached_activity() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let filter = g.add_node("filter", Some("Stream/Input/Audio"));
+    let client = g.add_node("music", Some("Stream/Output/Audio"));
+    g.connect(filter, sink);
+    let link = g.connect(client, filter);
+
+    assert_eq!(g.active_ids(), ids([client]));
+
+    assert!(g.set_link_active(link, false));
+    assert!(g.active_nodes().is_empty());
+
+    // Updates that change nothing are reported as such
+    assert!(!g.set_link_active(link, false));
+    assert!(g.active_nodes().is_empty());
+
+    assert!(g.set_link_active(link, true));
+    assert_eq!(g.active_ids(), ids([client]));
+}
+
+#[test]
+fn changes_to_one_sink_keep_the_paths_of_others() {
+    let mut g = TestGraph::new(config());
+    let speakers = g.add_node("speakers", Some("Audio/Sink"));
+    let headphones = g.add_node("headphones", Some("Audio/Sink"));
+    let music = g.add_node("music", Some("Stream/Output/Audio"));
+    let video = g.add_node("video", Some("Stream/Output/Audio"));
+    g.connect(music, speakers);
+    let link = g.connect(music, headphones);
+    g.connect(video, speakers);
+
+    assert_eq!(
+        g.paths(music),
+        [path(&["music", "headphones"]), path(&["music", "speakers"])]
+    );
+
+    assert!(g.set_link_active(link, false));
+    assert_eq!(g.active_ids(), ids([music, video]));
+    assert_eq!(g.paths(music), [path(&["music", "speakers"])]);
+
+    assert!(g.set_link_active(link, true));
+    g.graph.remove(speakers);
+    assert_eq!(g.active_ids(), ids([music]));
+    assert_eq!(g.paths(music), [path(&["music", "headphones"])]);
+}
+
+#[test]
+fn node_changes_update_cached_activity() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let node = g.add_node("node", Some("Stream/Input/Audio"));
+    g.connect(node, sink);
+
+    assert!(g.active_nodes().is_empty());
+
+    // The node only reveals itself as a client once its info arrives
+    assert!(g.update_node(
+        node,
+        NodeData {
+            media_class: Some(String::from("Stream/Output/Audio")),
+            ..Default::default()
+        }
+    ));
+    assert_eq!(g.active_ids(), ids([node]));
+}
+
+#[test]
+fn sink_whitelist() {
+    let mut config = config();
+    config.sink_whitelist = filters(r#"[{ "name": "^speakers$" }]"#);
+    let mut g = TestGraph::new(config);
+    let speakers = g.add_node("speakers", Some("Audio/Sink"));
+    let headphones = g.add_node("headphones", Some("Audio/Sink"));
+    let music = g.add_node("music", Some("Stream/Output/Audio"));
+    let video = g.add_node("video", Some("Stream/Output/Audio"));
+    g.connect(music, speakers);
+    g.connect(video, headphones);
+
+    assert_eq!(g.active_ids(), ids([music]));
+
+    // Renaming a sink changes whether it is watched
+    g.update_node(
+        headphones,
+        NodeData {
+            name: Some(String::from("speakers")),
+            ..Default::default()
+        },
+    );
+    assert_eq!(g.active_ids(), ids([music, video]));
+}
+
+#[test]
+fn device_sink_whitelist() {
+    let mut config = config();
+    config.sink_whitelist = filters(r#"[{ "device_form_factor": "^headphone$" }]"#);
+    let mut g = TestGraph::new(config);
+    let device = g.next_id();
+    let headphones = g.add_node_data(NodeData {
+        name: Some(String::from("headphones")),
+        media_class: Some(String::from("Audio/Sink")),
+        device_id: Some(device),
+        ..Default::default()
+    });
+    let music = g.add_node("music", Some("Stream/Output/Audio"));
+    g.connect(music, headphones);
+
+    // The sink is only watched once its device is known
+    assert!(g.active_nodes().is_empty());
+
+    let device_data = |form_factor: &str| DeviceData {
+        name: None,
+        description: None,
+        api: None,
+        bus: None,
+        form_factor: Some(String::from(form_factor)),
+    };
+    g.graph.insert(
+        device,
+        None,
+        PWObject::Device {
+            data: device_data("headphone"),
+        },
+    );
+    assert_eq!(g.active_ids(), ids([music]));
+
+    assert!(
+        g.graph
+            .update(device, None, PWObjectData::Device(device_data("speaker")))
+    );
+    assert!(g.active_nodes().is_empty());
+
+    g.graph
+        .update(device, None, PWObjectData::Device(device_data("headphone")));
+    assert_eq!(g.active_ids(), ids([music]));
+    g.graph.remove(device);
+    assert!(g.active_nodes().is_empty());
+}
+
+#[test]
+fn default_sink_whitelist() {
+    let mut config = config();
+    config.sink_whitelist = filters(r#"[{ "default": true }]"#);
+    let mut g = TestGraph::new(config);
+    let speakers = g.add_node("speakers", Some("Audio/Sink"));
+    let headphones = g.add_node("headphones", Some("Audio/Sink"));
+    let music = g.add_node("music", Some("Stream/Output/Audio"));
+    let video = g.add_node("video", Some("Stream/Output/Audio"));
+    g.connect(music, speakers);
+    g.connect(video, headphones);
+
+    assert!(g.active_nodes().is_empty());
+
+    assert!(
+        g.graph
+            .set_default_sink(DefaultSinkKey::Configured, Some(String::from("speakers")))
+    );
+    assert_eq!(g.active_ids(), ids([music]));
+
+    // The default sink in use has priority over the configured one
+    assert!(
+        g.graph
+            .set_default_sink(DefaultSinkKey::Default, Some(String::from("headphones")))
+    );
+    assert_eq!(g.active_ids(), ids([video]));
+
+    assert!(
+        !g.graph
+            .set_default_sink(DefaultSinkKey::Default, Some(String::from("headphones")))
+    );
+
+    assert!(g.graph.set_default_sink(DefaultSinkKey::All, None));
+    assert!(g.active_nodes().is_empty());
+}
+
+#[test]
+fn loopback() {
+    let mut g = TestGraph::new(config());
+    let speakers = g.add_node("speakers", Some("Audio/Sink"));
+    let virtual_sink = g.add_node("virtual sink", Some("Audio/Sink"));
+    let playback = g.add_node("loopback playback", Some("Stream/Output/Audio"));
+    let client = g.add_node("music", Some("Stream/Output/Audio"));
+    g.connect(client, virtual_sink);
+    g.connect(playback, speakers);
+
+    // The loopback moves media from the virtual sink to its playback stream inside the same
+    // process, so there is no link between them, and each is found from its own sink
+    assert_eq!(g.active_ids(), ids([client, playback]));
+    assert_eq!(g.paths(client), [path(&["music", "virtual sink"])]);
+    assert_eq!(
+        g.paths(playback),
+        [path(&["loopback playback", "speakers"])]
+    );
+}
+
+#[test]
+fn cycles_are_walked_once() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let a = g.add_node("a", None);
+    let b = g.add_node("b", None);
+    let client = g.add_node("music", Some("Stream/Output/Audio"));
+    g.connect(a, sink);
+    g.connect(b, a);
+    g.connect(a, b);
+    g.connect(a, a);
+    g.connect(client, b);
+
+    assert_eq!(g.active_ids(), ids([client]));
+    assert_eq!(g.paths(client), [path(&["music", "b", "a", "speakers"])]);
+
+    let walked_nodes = g.graph.get_watched_nodes();
+    assert_eq!(
+        walked_nodes.into_iter().collect::<BTreeSet<_>>(),
+        ids([sink, a, b, client])
+    );
+}
+
+#[test]
+fn sink_feeding_itself() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let client = g.add_node("music", Some("Stream/Output/Audio"));
+    g.connect(sink, sink);
+    g.connect(client, sink);
+
+    assert_eq!(g.active_ids(), ids([client]));
+}
+
+#[test]
+fn removals() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let filter = g.add_node("filter", Some("Stream/Input/Audio"));
+    let music = g.add_node("music", Some("Stream/Output/Audio"));
+    let video = g.add_node("video", Some("Stream/Output/Audio"));
+    let game = g.add_node("game", Some("Stream/Output/Audio"));
+    g.connect(filter, sink);
+    let music_link = g.connect(music, filter);
+    g.connect(video, filter);
+    let game_output = g.add_port(game, Direction::Output);
+    let filter_input = g.add_port(filter, Direction::Input);
+    g.add_link(game_output, filter_input);
+
+    assert_eq!(g.active_ids(), ids([music, video, game]));
+
+    assert!(matches!(
+        g.graph.remove(music_link),
+        Some(PWObject::Link { .. })
+    ));
+    assert_eq!(g.active_ids(), ids([video, game]));
+
+    assert!(matches!(g.graph.remove(video), Some(PWObject::Node { .. })));
+    assert_eq!(g.active_ids(), ids([game]));
+
+    assert!(matches!(
+        g.graph.remove(game_output),
+        Some(PWObject::Port { .. })
+    ));
+    assert!(g.active_nodes().is_empty());
+
+    assert!(g.graph.remove(game_output).is_none());
+
+    assert!(matches!(g.graph.remove(sink), Some(PWObject::Node { .. })));
+    assert!(g.graph.get_watched_nodes().is_empty());
+}
+
+#[test]
+fn link_registered_before_its_ports_and_nodes() {
+    let mut g = TestGraph::new(config());
+    let (sink, sink_input, link, client_output, client) = (1, 2, 3, 4, 5);
+
+    g.insert_link(link, client_output, sink_input);
+    assert!(g.active_nodes().is_empty());
+
+    g.insert_node(
+        sink,
+        NodeData {
+            name: Some(String::from("speakers")),
+            media_class: Some(String::from("Audio/Sink")),
+            ..Default::default()
+        },
+    );
+    assert!(g.active_nodes().is_empty());
+
+    g.insert_port(sink_input, sink, Direction::Input);
+    assert!(g.active_nodes().is_empty());
+
+    g.insert_node(client, NodeData::default());
+    assert!(g.active_nodes().is_empty());
+
+    // The walk stopped at the link, as its output port was unknown
+    g.insert_port(client_output, client, Direction::Output);
+    assert_eq!(g.active_ids(), ids([client]));
+}
+
+#[test]
+fn node_registered_after_its_ports() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let (client, client_output) = (100, 101);
+    g.insert_port(client_output, client, Direction::Output);
+    let sink_input = g.add_port(sink, Direction::Input);
+    g.add_link(client_output, sink_input);
+
+    assert!(g.active_nodes().is_empty());
+
+    g.insert_node(
+        client,
+        NodeData {
+            name: Some(String::from("music")),
+            media_class: Some(String::from("Stream/Output/Audio")),
+            ..Default::default()
+        },
+    );
+    assert_eq!(g.active_ids(), ids([client]));
+}
+
+#[test]
+fn updates_of_missing_or_mismatched_objects_are_ignored() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let client = g.add_node("music", Some("Stream/Output/Audio"));
+    let link = g.connect(client, sink);
+
+    assert!(!g.set_link_active(1000, false));
+    assert!(!g.update_node(
+        link,
+        NodeData {
+            mute: Some(true),
+            ..Default::default()
+        }
+    ));
+    assert!(!g.set_link_active(client, false));
+    assert_eq!(g.active_ids(), ids([client]));
+}
+
+#[test]
+fn updates_with_stale_serials_are_ignored() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let client = g.add_node("music", Some("Stream/Output/Audio"));
+    let output_port = g.add_port(client, Direction::Output);
+    let input_port = g.add_port(sink, Direction::Input);
+    let link = g.next_id();
+    let data = LinkData {
+        input_port: Some(input_port),
+        output_port: Some(output_port),
+        active: Some(true),
+        passive: Some(false),
+    };
+    g.graph.insert(link, Some(7), PWObject::Link { data });
+
+    assert!(!g.update_link(link, Some(6), link_state(Some(false), None)));
+    assert_eq!(g.active_ids(), ids([client]));
+
+    assert!(g.update_link(link, Some(7), link_state(Some(false), None)));
+    assert!(g.active_nodes().is_empty());
+}
+
+#[test]
+fn reused_ids_replace_older_objects() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let client = g.add_node("music", Some("Stream/Output/Audio"));
+    let output_port = g.add_port(client, Direction::Output);
+    let input_port = g.add_port(sink, Direction::Input);
+    g.add_link(output_port, input_port);
+
+    assert_eq!(g.active_ids(), ids([client]));
+
+    // The removal of the port was missed, and its id is reused by a node
+    g.insert_node(
+        output_port,
+        NodeData {
+            name: Some(String::from("other")),
+            media_class: Some(String::from("Stream/Output/Audio")),
+            ..Default::default()
+        },
+    );
+    assert!(g.active_nodes().is_empty());
+
+    // The removal of the sink was missed, and its id is reused by a sink without ports
+    let video = g.add_node("video", Some("Stream/Output/Audio"));
+    g.connect(video, sink);
+    assert_eq!(g.active_ids(), ids([video]));
+    g.insert_node(
+        sink,
+        NodeData {
+            name: Some(String::from("headphones")),
+            media_class: Some(String::from("Audio/Sink")),
+            ..Default::default()
+        },
+    );
+    assert!(g.active_nodes().is_empty());
+}
+
+#[test]
+fn node_blacklist() {
+    let mut config = config();
+    config.node_blacklist = filters(r#"[{ "name": "^notifications$" }, { "name": "^filter$" }]"#);
+    let mut g = TestGraph::new(config);
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let filter = g.add_node("filter", Some("Stream/Input/Audio"));
+    let notifications = g.add_node("notifications", Some("Stream/Output/Audio"));
+    let music = g.add_node("music", Some("Stream/Output/Audio"));
+    let video = g.add_node("video", Some("Stream/Output/Audio"));
+    g.connect(notifications, sink);
+    g.connect(filter, sink);
+    g.connect(music, filter);
+    g.connect(video, filter);
+    g.connect(video, sink);
+
+    let active_nodes = g.active_nodes();
+    assert_eq!(active_nodes.len(), 3);
+    assert!(active_nodes[&notifications].is_blacklisted);
+    // Nodes only reached through blacklisted nodes are blacklisted
+    assert!(active_nodes[&music].is_blacklisted);
+    assert!(active_nodes[&music].paths[0].is_blacklisted);
+    // A single path without blacklisted nodes is enough
+    assert!(!active_nodes[&video].is_blacklisted);
+}
+
+#[test]
+fn ring_tones_preset_does_not_match_calls() {
+    let mut config = config();
+    config.node_blacklist = Preset::RingTones.filters();
+    let mut g = TestGraph::new(config);
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let ring_tone = g.add_node_data(NodeData {
+        name: Some(String::from("ring-tone")),
+        media_class: Some(String::from("Stream/Output/Audio")),
+        media_role: Some(String::from("Alert")),
+        ..Default::default()
+    });
+    let call = g.add_node_data(NodeData {
+        name: Some(String::from("call")),
+        media_class: Some(String::from("Stream/Output/Audio")),
+        media_role: Some(String::from("Communication")),
+        ..Default::default()
+    });
+    g.connect(ring_tone, sink);
+    g.connect(call, sink);
+
+    let active_nodes = g.active_nodes();
+    assert!(active_nodes[&ring_tone].is_blacklisted);
+    assert!(!active_nodes[&call].is_blacklisted);
+}
+
+#[test]
+fn route_blacklist() {
+    let mut config = config();
+    config.route_blacklist =
+        filters(r#"[{ "node": { "name": "^music$" }, "sink": { "name": "^headphones$" } }]"#);
+    let mut g = TestGraph::new(config);
+    let speakers = g.add_node("speakers", Some("Audio/Sink"));
+    let headphones = g.add_node("headphones", Some("Audio/Sink"));
+    let music = g.add_node("music", Some("Stream/Output/Audio"));
+    let video = g.add_node("video", Some("Stream/Output/Audio"));
+    g.connect(music, headphones);
+    g.connect(video, headphones);
+
+    let active_nodes = g.active_nodes();
+    assert!(active_nodes[&music].is_blacklisted);
+    assert!(!active_nodes[&video].is_blacklisted);
+
+    g.connect(music, speakers);
+    assert!(!g.active_nodes()[&music].is_blacklisted);
+}
+
+#[test]
+fn passive_links() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let client = g.add_node("music", Some("Stream/Output/Audio"));
+    let link = g.connect(client, sink);
+    g.update_link(link, None, link_state(None, Some(true)));
+
+    assert!(g.active_nodes().is_empty());
+
+    let mut config = config();
+    config.ignore_passive = false;
+    let mut g = TestGraph::new(config);
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let client = g.add_node("music", Some("Stream/Output/Audio"));
+    let link = g.connect(client, sink);
+    g.update_link(link, None, link_state(None, Some(true)));
+
+    assert_eq!(g.active_ids(), ids([client]));
+}
+
+#[test]
+fn passive_nodes() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let filter = g.add_node("filter", Some("Stream/Input/Audio"));
+    let music = g.add_node("music", Some("Stream/Output/Audio"));
+    let analyzer = g.add_node_data(NodeData {
+        name: Some(String::from("analyzer")),
+        media_class: Some(String::from("Stream/Output/Audio")),
+        passive: Some(String::from("out")),
+        ..Default::default()
+    });
+    g.connect(filter, sink);
+    g.connect(music, filter);
+    g.connect(analyzer, sink);
+
+    assert_eq!(g.active_ids(), ids([music]));
+
+    g.update_node(
+        filter,
+        NodeData {
+            passive: Some(String::from("in")),
+            ..Default::default()
+        },
+    );
+    assert!(g.active_nodes().is_empty());
+
+    g.update_node(
+        filter,
+        NodeData {
+            passive: Some(String::from("false")),
+            ..Default::default()
+        },
+    );
+    assert_eq!(g.active_ids(), ids([music]));
+}
+
+#[test]
+fn recording() {
+    let mut config = config();
+    config.detect_recording = true;
+    config.recorder_whitelist = filters(r#"[{ "name": "^recorder$" }]"#);
+    let mut g = TestGraph::new(config);
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let recorder = g.add_node("recorder", Some("Stream/Input/Audio"));
+    let meter = g.add_node("meter", Some("Stream/Input/Audio"));
+    let monitor = g.next_id();
+    let data = PortData {
+        name: Some(String::from("monitor_FL")),
+        node_id: Some(sink),
+        direction: Some(Direction::Output),
+        is_terminal: None,
+        is_monitor: Some(true),
+    };
+    g.graph.insert(monitor, None, PWObject::Port { data });
+    let recorder_input = g.add_port(recorder, Direction::Input);
+    let recorder_link = g.add_link(monitor, recorder_input);
+    // Links from monitor ports are usually passive, but are followed anyway
+    g.update_link(recorder_link, None, link_state(None, Some(true)));
+    let meter_input = g.add_port(meter, Direction::Input);
+    g.add_link(monitor, meter_input);
+
+    assert_eq!(g.active_ids(), ids([recorder]));
+    assert_eq!(g.paths(recorder), [path(&["recorder", "speakers"])]);
+
+    g.set_link_active(recorder_link, false);
+    assert!(g.active_nodes().is_empty());
+}
+
+#[test]
+fn recording_is_ignored_by_default() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let recorder = g.add_node("recorder", Some("Stream/Input/Audio"));
+    g.connect(sink, recorder);
+
+    assert!(g.active_nodes().is_empty());
+}
+
+#[test]
+fn muted_and_quiet_nodes() {
+    let mut config = config();
+    config.ignore_muted = true;
+    config.minimum_volume = Some(20.0);
+    let mut g = TestGraph::new(config);
+    assert!(g.graph.watches_volume());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let music = g.add_node("music", Some("Stream/Output/Audio"));
+    let video = g.add_node("video", Some("Stream/Output/Audio"));
+    g.connect(music, sink);
+    g.connect(video, sink);
+
+    assert_eq!(g.active_ids(), ids([music, video]));
+
+    g.update_node(
+        music,
+        NodeData {
+            mute: Some(true),
+            ..Default::default()
+        },
+    );
+    // A linear volume of 0.001 is 10% on the cubic scale of volume controls
+    g.update_node(
+        video,
+        NodeData {
+            volume: Some(0.001),
+            ..Default::default()
+        },
+    );
+    assert!(g.active_nodes().is_empty());
+
+    g.update_node(
+        music,
+        NodeData {
+            mute: Some(false),
+            ..Default::default()
+        },
+    );
+    g.update_node(
+        video,
+        NodeData {
+            volume: Some(0.5),
+            ..Default::default()
+        },
+    );
+    assert_eq!(g.active_ids(), ids([music, video]));
+
+    // Muting the sink silences every path into it
+    g.update_node(
+        sink,
+        NodeData {
+            mute: Some(true),
+            ..Default::default()
+        },
+    );
+    assert!(g.active_nodes().is_empty());
+}
+
+#[test]
+fn mute_is_tracked_only_if_configured() {
+    let mut g = TestGraph::new(config());
+    assert!(!g.graph.watches_volume());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let client = g.add_node_data(NodeData {
+        name: Some(String::from("music")),
+        media_class: Some(String::from("Stream/Output/Audio")),
+        mute: Some(true),
+        ..Default::default()
+    });
+    g.connect(client, sink);
+
+    assert_eq!(g.active_ids(), ids([client]));
+}
+
+#[test]
+fn watched_nodes() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let filter = g.add_node("filter", Some("Stream/Input/Audio"));
+    let client = g.add_node("music", Some("Stream/Output/Audio"));
+    let paused = g.add_node("paused", Some("Stream/Output/Audio"));
+    let unlinked = g.add_node("unlinked", Some("Stream/Output/Audio"));
+    g.connect(filter, sink);
+    g.connect(client, filter);
+    let paused_link = g.connect(paused, filter);
+    g.set_link_active(paused_link, false);
+
+    // Before the first evaluation, only the sinks are known to be watched
+    assert_eq!(
+        g.graph
+            .get_watched_nodes()
+            .into_iter()
+            .collect::<BTreeSet<_>>(),
+        ids([sink])
+    );
+
+    g.active_nodes();
+    let watched_nodes = g.graph.get_watched_nodes();
+    assert_eq!(
+        watched_nodes.into_iter().collect::<BTreeSet<_>>(),
+        ids([sink, filter, client])
+    );
+    assert!(!g.graph.get_watched_nodes().contains(&unlinked));
+}
+
+#[test]
+fn client_names_in_paths() {
+    let mut g = TestGraph::new(config());
+    let sink = g.add_node_data(NodeData {
+        name: Some(String::from("alsa_output.pci-0000_00_1f.3.analog-stereo")),
+        description: Some(String::from("Built-in Audio")),
+        media_class: Some(String::from("Audio/Sink")),
+        ..Default::default()
+    });
+    let browser = g.next_id();
+    let data = ClientData {
+        app_name: Some(String::from("Firefox")),
+        process_binary: Some(String::from("firefox")),
+        app_id: None,
+    };
+    g.graph.insert(browser, None, PWObject::Client { data });
+    let tab = g.add_node_data(NodeData {
+        name: Some(String::from("AudioStream")),
+        media_class: Some(String::from("Stream/Output/Audio")),
+        client_id: Some(browser),
+        ..Default::default()
+    });
+    let player = g.add_node_data(NodeData {
+        name: Some(String::from("output")),
+        app_name: Some(String::from("mpv")),
+        media_class: Some(String::from("Stream/Output/Audio")),
+        client_id: Some(browser),
+        ..Default::default()
+    });
+    let anonymous = g.add_node_data(NodeData {
+        media_class: Some(String::from("Stream/Output/Audio")),
+        ..Default::default()
+    });
+    g.connect(tab, sink);
+    g.connect(player, sink);
+    g.connect(anonymous, sink);
+
+    assert_eq!(g.paths(tab), [path(&["Firefox", "Built-in Audio"])]);
+    assert_eq!(g.paths(player), [path(&["mpv", "Built-in Audio"])]);
+    assert_eq!(
+        g.paths(anonymous),
+        [path(&[anonymous.to_string().as_str(), "Built-in Audio"])]
+    );
+}
+
+#[test]
+fn client_changes_update_cached_activity() {
+    let mut config = config();
+    config.node_blacklist = filters(r#"[{ "app_name": "^Firefox$" }]"#);
+    let mut g = TestGraph::new(config);
+    let sink = g.add_node("speakers", Some("Audio/Sink"));
+    let browser = g.next_id();
+    let data = ClientData {
+        app_name: None,
+        process_binary: None,
+        app_id: None,
+    };
+    g.graph.insert(browser, None, PWObject::Client { data });
+    let tab = g.add_node_data(NodeData {
+        name: Some(String::from("AudioStream")),
+        media_class: Some(String::from("Stream/Output/Audio")),
+        client_id: Some(browser),
+        ..Default::default()
+    });
+    g.connect(tab, sink);
+
+    assert!(!g.active_nodes()[&tab].is_blacklisted);
+
+    g.graph.update(
+        browser,
+        None,
+        PWObjectData::Client(ClientData {
+            app_name: Some(String::from("Firefox")),
+            process_binary: None,
+            app_id: None,
+        }),
+    );
+    assert!(g.active_nodes()[&tab].is_blacklisted);
+    assert_eq!(g.paths(tab), [path(&["Firefox", "speakers"])]);
+
+    // Changes of the client a node is moved to are also followed
+    let player = g.next_id();
+    let data = ClientData {
+        app_name: Some(String::from("mpv")),
+        process_binary: None,
+        app_id: None,
+    };
+    g.graph.insert(player, None, PWObject::Client { data });
+    g.update_node(
+        tab,
+        NodeData {
+            client_id: Some(player),
+            ..Default::default()
+        },
+    );
+    assert!(!g.active_nodes()[&tab].is_blacklisted);
+
+    g.graph.update(
+        player,
+        None,
+        PWObjectData::Client(ClientData {
+            app_name: Some(String::from("Firefox")),
+            process_binary: None,
+            app_id: None,
+        }),
+    );
+    assert!(g.active_nodes()[&tab].is_blacklisted);
+}
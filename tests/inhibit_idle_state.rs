@@ -0,0 +1,379 @@
+// Copyright (C) 2026  Rafael Carvalho <contact@rafaelrc.com>
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Tests of the [InhibitIdleState] decisions, driven by a virtual [Clock].
+//!
+//! Active nodes are set at given seconds after the start, and the clock is moved as the replay of a
+//! trace would do, expiring the timer at each update requested by the state on the way. The
+//! notified changes are collected with the second at which they were sent.
+//!
+//! Run with `cargo test`.
+
+use chrono::{DateTime, Duration, FixedOffset};
+use nix::sys::epoll::{Epoll, EpollCreateFlags};
+
+#[allow(dead_code)]
+#[path = "../src/inhibit_idle_state.rs"]
+mod inhibit_idle_state;
+use inhibit_idle_state::{Clock, InhibitIdleState, InhibitIdleStateEvent};
+
+#[allow(dead_code)]
+#[path = "../src/message_queue.rs"]
+mod message_queue;
+use message_queue::{MessageQueueReceiver, message_queue};
+
+#[allow(dead_code)]
+#[path = "../src/schedule.rs"]
+mod schedule;
+use schedule::ScheduleRule;
+
+/// Stand-in for the PipeWire connection module, with only the types used by the state
+#[path = "../src/pipewire_connection"]
+mod pipewire_connection {
+    #[allow(dead_code)]
+    pub mod graph;
+
+    pub type RemoteId = usize;
+}
+use pipewire_connection::graph::{ActiveNode, ActivePath, object::Id};
+
+/// A Monday
+const START: &str = "2026-01-05T12:00:00+00:00";
+
+/// Options of the [InhibitIdleState], in seconds
+#[derive(Default)]
+struct Options {
+    minimum_duration: Option<i64>,
+    release_delay: Option<i64>,
+    minimum_toggle_interval: Option<i64>,
+    maximum_duration: Option<i64>,
+    schedule: Vec<ScheduleRule>,
+}
+
+/// A change notified by the [InhibitIdleState]
+#[derive(Debug, PartialEq)]
+enum Change {
+    InhibitIdle(bool),
+    /// The client of each active path
+    ActivePaths(Vec<String>),
+}
+
+/// An [InhibitIdleState] on a virtual clock, with the changes it notified so far
+struct TestState {
+    state: InhibitIdleState<InhibitIdleStateEvent>,
+    receiver: MessageQueueReceiver<InhibitIdleStateEvent>,
+    start: DateTime<FixedOffset>,
+    changes: Vec<(i64, Change)>,
+    _epoll: Epoll,
+}
+
+impl TestState {
+    fn new(start: &str, options: Options) -> Self {
+        let start = DateTime::parse_from_rfc3339(start).unwrap();
+        let epoll = Epoll::new(EpollCreateFlags::empty()).unwrap();
+        let (sender, receiver) = message_queue(&epoll, 0).unwrap();
+        let state = InhibitIdleState::new(
+            options.minimum_duration.map(Duration::seconds),
+            options.release_delay.map(Duration::seconds),
+            options.minimum_toggle_interval.map(Duration::seconds),
+            options.maximum_duration.map(Duration::seconds),
+            options.schedule,
+            Clock::Virtual(start),
+            sender,
+        );
+
+        Self {
+            state,
+            receiver,
+            start,
+            changes: Vec::new(),
+            _epoll: epoll,
+        }
+    }
+
+    /// Moves the clock to the given second, expiring the timer at every update due before it
+    fn advance(&mut self, second: i64) {
+        let time = self.start + Duration::seconds(second);
+        while let Some(next_update) = self.state.get_next_update() {
+            let next_update = next_update.with_timezone(self.start.offset());
+            if next_update > time {
+                break;
+            }
+            self.state.set_time(next_update);
+            self.state.timer_expired();
+            self.collect(next_update);
+        }
+        self.state.set_time(time);
+    }
+
+    /// Sets the clients that are playing at the given second
+    fn play(&mut self, second: i64, clients: &[(Id, &str)]) {
+        self.advance(second);
+        let active_nodes = clients
+            .iter()
+            .map(|(id, name)| {
+                let path = ActivePath {
+                    nodes: vec![String::from(*name), String::from("speakers")],
+                    is_blacklisted: false,
+                };
+                let active_node = ActiveNode {
+                    is_blacklisted: false,
+                    paths: vec![path],
+                };
+                (*id, active_node)
+            })
+            .collect();
+        self.state.set_active_nodes(0, active_nodes);
+        self.collect(self.start + Duration::seconds(second));
+    }
+
+    /// Re-arms the state at the given second, as done on user input
+    fn rearm(&mut self, second: i64) {
+        self.advance(second);
+        self.state.rearm();
+        self.collect(self.start + Duration::seconds(second));
+    }
+
+    /// Stores the changes notified since the last call, as sent at the given time
+    fn collect(&mut self, time: DateTime<FixedOffset>) {
+        let second = (time - self.start).num_seconds();
+        while let Some(event) = self.receiver.try_recv().unwrap() {
+            let change = match event {
+                InhibitIdleStateEvent::InhibitIdle(inhibit_idle) => {
+                    Change::InhibitIdle(inhibit_idle)
+                }
+                InhibitIdleStateEvent::ActivePaths(paths) => Change::ActivePaths(
+                    paths
+                        .iter()
+                        .filter_map(|path| path.client().map(String::from))
+                        .collect(),
+                ),
+                InhibitIdleStateEvent::TimerExpired => continue,
+            };
+            self.changes.push((second, change));
+        }
+    }
+
+    /// Returns the changes of the inhibit state notified so far, with the second they were sent at
+    fn toggles(&self) -> Vec<(i64, bool)> {
+        self.changes
+            .iter()
+            .filter_map(|(second, change)| match change {
+                Change::InhibitIdle(inhibit_idle) => Some((*second, *inhibit_idle)),
+                Change::ActivePaths(_) => None,
+            })
+            .collect()
+    }
+
+    /// Returns the changes of the active paths notified so far, with the second they were sent at
+    fn active_paths(&self) -> Vec<(i64, Vec<&str>)> {
+        self.changes
+            .iter()
+            .filter_map(|(second, change)| match change {
+                Change::ActivePaths(clients) => {
+                    Some((*second, clients.iter().map(String::as_str).collect()))
+                }
+                Change::InhibitIdle(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Parses schedule rules, written as they would be in the config file, but in JSON
+fn schedule(json: &str) -> Vec<ScheduleRule> {
+    serde_json::from_str(json).unwrap()
+}
+
+const MUSIC: (Id, &str) = (1, "music");
+const VIDEO: (Id, &str) = (2, "video");
+
+#[test]
+fn no_decision_before_the_graph_is_known() {
+    let mut s = TestState::new(START, Options::default());
+    s.advance(60);
+    assert!(s.changes.is_empty());
+
+    s.play(60, &[]);
+    assert!(s.changes.is_empty());
+    assert_eq!(s.state.get_next_update(), None);
+}
+
+#[test]
+fn minimum_duration_is_tracked_per_stream() {
+    let mut s = TestState::new(
+        START,
+        Options {
+            minimum_duration: Some(5),
+            ..Default::default()
+        },
+    );
+    s.play(0, &[MUSIC]);
+    s.play(3, &[VIDEO]);
+    s.advance(7);
+    // Media has played for 7 seconds, but no single stream for 5
+    assert!(s.toggles().is_empty());
+
+    s.advance(20);
+    assert_eq!(s.toggles(), [(8, true)]);
+    assert_eq!(s.active_paths(), [(8, vec!["video"])]);
+}
+
+#[test]
+fn release_delay_is_cancelled_when_media_resumes() {
+    let mut s = TestState::new(
+        START,
+        Options {
+            release_delay: Some(10),
+            ..Default::default()
+        },
+    );
+    s.play(0, &[MUSIC]);
+    s.play(5, &[]);
+    s.play(12, &[MUSIC]);
+    s.advance(30);
+    assert_eq!(s.toggles(), [(0, true)]);
+
+    // The release delay starts again once media stops once more
+    s.play(30, &[]);
+    s.advance(60);
+    assert_eq!(s.toggles(), [(0, true), (40, false)]);
+}
+
+#[test]
+fn still_playing_nodes_keep_idle_inhibited() {
+    let mut s = TestState::new(
+        START,
+        Options {
+            minimum_duration: Some(5),
+            ..Default::default()
+        },
+    );
+    s.play(0, &[MUSIC]);
+    // The player opens a new stream for the next track, that has not reached the minimum duration
+    s.play(10, &[VIDEO]);
+    s.advance(60);
+    assert_eq!(s.toggles(), [(5, true)]);
+    assert_eq!(s.active_paths(), [(5, vec!["music"]), (10, vec!["video"])]);
+
+    s.play(60, &[]);
+    assert_eq!(s.toggles(), [(5, true), (60, false)]);
+}
+
+#[test]
+fn toggle_inside_minimum_interval_is_delayed() {
+    let mut s = TestState::new(
+        START,
+        Options {
+            minimum_toggle_interval: Some(30),
+            ..Default::default()
+        },
+    );
+    s.play(0, &[MUSIC]);
+    s.play(5, &[]);
+    s.advance(29);
+    assert_eq!(s.toggles(), [(0, true)]);
+
+    s.advance(60);
+    assert_eq!(s.toggles(), [(0, true), (30, false)]);
+
+    // Media that resumes before the delayed toggle cancels it
+    s.play(70, &[MUSIC]);
+    s.play(80, &[]);
+    s.play(90, &[MUSIC]);
+    s.advance(200);
+    assert_eq!(s.toggles(), [(0, true), (30, false), (70, true)]);
+}
+
+#[test]
+fn active_path_changes_are_rate_limited() {
+    let mut s = TestState::new(
+        START,
+        Options {
+            minimum_toggle_interval: Some(30),
+            ..Default::default()
+        },
+    );
+    s.play(0, &[MUSIC]);
+    s.play(5, &[MUSIC, VIDEO]);
+    s.play(10, &[VIDEO]);
+    s.advance(29);
+    assert_eq!(s.active_paths(), [(0, vec!["music"])]);
+
+    s.advance(60);
+    assert_eq!(s.active_paths(), [(0, vec!["music"]), (30, vec!["video"])]);
+    assert_eq!(s.toggles(), [(0, true)]);
+}
+
+#[test]
+fn maximum_duration_is_rearmed() {
+    let mut s = TestState::new(
+        START,
+        Options {
+            maximum_duration: Some(60),
+            ..Default::default()
+        },
+    );
+    s.play(0, &[MUSIC]);
+    s.advance(120);
+    assert_eq!(s.toggles(), [(0, true), (60, false)]);
+
+    // User input re-arms the inhibitor while media is still playing
+    s.rearm(120);
+    s.advance(200);
+    assert_eq!(
+        s.toggles(),
+        [(0, true), (60, false), (120, true), (180, false)]
+    );
+
+    // So does media that stops and plays again
+    s.play(200, &[]);
+    s.play(210, &[MUSIC]);
+    s.advance(260);
+    assert_eq!(
+        s.toggles(),
+        [
+            (0, true),
+            (60, false),
+            (120, true),
+            (180, false),
+            (210, true)
+        ]
+    );
+}
+
+#[test]
+fn schedule_boundaries() {
+    let mut s = TestState::new(
+        "2026-01-05T21:00:00+05:00",
+        Options {
+            schedule: schedule(r#"[{ "from": "22:00", "to": "06:00", "inhibit": false }]"#),
+            ..Default::default()
+        },
+    );
+    s.play(0, &[MUSIC]);
+    // Rules are evaluated in the time zone of the clock, whatever the local one is
+    s.advance(10 * 60 * 60);
+    assert_eq!(
+        s.toggles(),
+        [(0, true), (60 * 60, false), (9 * 60 * 60, true)]
+    );
+    assert_eq!(
+        s.state
+            .get_next_update()
+            .map(|next_update| next_update.with_timezone(&s.start.timezone())),
+        DateTime::parse_from_rfc3339("2026-01-06T22:00:00+05:00").ok()
+    );
+}
@@ -0,0 +1,172 @@
+// Copyright (C) 2026  Rafael Carvalho <contact@rafaelrc.com>
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Tests of the [schedule::schedule_state_at] evaluation, including around daylight saving time
+//! changes, in a time zone built for the tests.
+//!
+//! Run with `cargo test`.
+
+use chrono::{
+    DateTime, Datelike, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday,
+};
+
+#[allow(dead_code)]
+#[path = "../src/schedule.rs"]
+mod schedule;
+use schedule::{ScheduleRule, schedule_state_at};
+
+/// Central European time around the end of daylight saving time on 2026-10-25, when clocks go back
+/// from 03:00 CEST to 02:00 CET, so that local times from 02:00 to 03:00 happen twice
+#[derive(Clone, Copy, Debug)]
+struct FallBack;
+
+impl FallBack {
+    const SUMMER: i32 = 2 * 60 * 60;
+    const WINTER: i32 = 60 * 60;
+
+    fn summer() -> FixedOffset {
+        FixedOffset::east_opt(Self::SUMMER).unwrap()
+    }
+
+    fn winter() -> FixedOffset {
+        FixedOffset::east_opt(Self::WINTER).unwrap()
+    }
+
+    fn local(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 25)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+}
+
+impl TimeZone for FallBack {
+    type Offset = FixedOffset;
+
+    fn from_offset(_offset: &FixedOffset) -> Self {
+        FallBack
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+        self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+        if *local < Self::local(2, 0) {
+            LocalResult::Single(Self::summer())
+        } else if *local < Self::local(3, 0) {
+            LocalResult::Ambiguous(Self::summer(), Self::winter())
+        } else {
+            LocalResult::Single(Self::winter())
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+        self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+        if *utc < Self::local(1, 0) {
+            Self::summer()
+        } else {
+            Self::winter()
+        }
+    }
+}
+
+/// Parses schedule rules, written as they would be in the config file, but in JSON
+fn schedule(json: &str) -> Vec<ScheduleRule> {
+    serde_json::from_str(json).unwrap()
+}
+
+fn utc(time: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+}
+
+/// Gets the next change of the schedule, in UTC
+fn next_change<Tz: TimeZone>(rules: &[ScheduleRule], now: DateTime<Tz>) -> Option<DateTime<Utc>> {
+    schedule_state_at(rules, now)
+        .next_change
+        .map(|next_change| next_change.to_utc())
+}
+
+#[test]
+fn rules_wrap_around_midnight() {
+    let rules = schedule(r#"[{ "from": "22:00", "to": "06:00", "inhibit": false }]"#);
+    let offset = FixedOffset::east_opt(5 * 60 * 60).unwrap();
+
+    let evening = utc("2026-01-05T21:00:00+05:00").with_timezone(&offset);
+    let state = schedule_state_at(&rules, evening);
+    assert_eq!(state.inhibit, None);
+    assert_eq!(
+        next_change(&rules, evening),
+        Some(utc("2026-01-05T22:00:00+05:00"))
+    );
+
+    let night = utc("2026-01-06T03:00:00+05:00").with_timezone(&offset);
+    let state = schedule_state_at(&rules, night);
+    assert_eq!(state.inhibit, Some(false));
+    assert_eq!(
+        next_change(&rules, night),
+        Some(utc("2026-01-06T06:00:00+05:00"))
+    );
+}
+
+#[test]
+fn rules_only_apply_on_their_days() {
+    let rules = schedule(r#"[{ "days": ["Sat", "Sun"], "inhibit": true }]"#);
+    let offset = FixedOffset::east_opt(0).unwrap();
+
+    let friday = utc("2026-01-09T12:00:00+00:00").with_timezone(&offset);
+    assert_eq!(friday.weekday(), Weekday::Fri);
+    assert_eq!(schedule_state_at(&rules, friday).inhibit, None);
+    assert_eq!(
+        next_change(&rules, friday),
+        Some(utc("2026-01-10T00:00:00+00:00"))
+    );
+
+    let saturday = utc("2026-01-10T12:00:00+00:00").with_timezone(&offset);
+    assert_eq!(schedule_state_at(&rules, saturday).inhibit, Some(true));
+}
+
+#[test]
+fn boundaries_repeated_by_fall_back_are_after_now() {
+    let rules = schedule(r#"[{ "from": "02:30", "to": "06:00", "inhibit": false }]"#);
+
+    // 02:10 CET, after clocks went back, while 02:30 CEST is already past
+    let now = utc("2026-10-25T01:10:00+00:00").with_timezone(&FallBack);
+    assert_eq!(now.naive_local(), FallBack::local(2, 10));
+    assert_eq!(
+        next_change(&rules, now),
+        Some(utc("2026-10-25T01:30:00+00:00"))
+    );
+
+    // 02:10 CEST, before clocks went back, when 02:30 CEST is still ahead
+    let now = utc("2026-10-25T00:10:00+00:00").with_timezone(&FallBack);
+    assert_eq!(now.naive_local(), FallBack::local(2, 10));
+    assert_eq!(
+        next_change(&rules, now),
+        Some(utc("2026-10-25T00:30:00+00:00"))
+    );
+
+    // Every boundary is strictly after now, also when now is the boundary itself
+    let mut now = utc("2026-10-24T23:00:00+00:00").with_timezone(&FallBack);
+    let end = utc("2026-10-26T00:00:00+00:00");
+    while let Some(boundary) = next_change(&rules, now).filter(|boundary| *boundary < end) {
+        assert!(boundary > now);
+        now = boundary.with_timezone(&FallBack);
+    }
+}